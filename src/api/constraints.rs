@@ -5,11 +5,13 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
 use serde::Serialize;
 
+use crate::api::ApiError;
+use crate::locks;
+use crate::paths::{self, PathError};
 use crate::state::AppState;
 use acp::constraints::Constraints;
 
@@ -21,44 +23,32 @@ pub struct ConstraintResponse {
 }
 
 /// GET /constraints/*path - Get constraints for a file path
+///
+/// Paths are resolved like `/files`; ambiguous suffixes return 409.
 pub async fn get_constraints(
     State(state): State<AppState>,
     Path(path): Path<String>,
-) -> Result<Json<ConstraintResponse>, StatusCode> {
+) -> Result<Json<ConstraintResponse>, ApiError> {
     let cache = state.cache_async().await;
 
-    let path_normalized = path.trim_start_matches('/');
-
-    // Get constraints from the constraint index
-    let (constraints, lock_level) = if let Some(constraint_index) = &cache.constraints {
-        let file_constraints = constraint_index.by_file.get(path_normalized).cloned();
-
-        // Check lock level
-        let lock = if constraint_index
-            .by_lock_level
-            .get("frozen")
-            .map(|files| files.iter().any(|f| f == path_normalized))
-            .unwrap_or(false)
-        {
-            Some("frozen".to_string())
-        } else if constraint_index
-            .by_lock_level
-            .get("restricted")
-            .map(|files| files.iter().any(|f| f == path_normalized))
-            .unwrap_or(false)
-        {
-            Some("restricted".to_string())
-        } else {
-            None
-        };
+    let path_normalized = match paths::resolve(state.project_root(), &cache.files, &path) {
+        Ok(resolved) => resolved,
+        // Files that are not indexed yet have no constraints
+        Err(PathError::NotFound { path, .. }) => path,
+        Err(e) => return Err(e.into()),
+    };
 
-        (file_constraints, lock)
-    } else {
-        (None, None)
+    // Get constraints and lock level from the constraint index
+    let (constraints, lock_level) = match &cache.constraints {
+        Some(constraint_index) => (
+            constraint_index.by_file.get(&path_normalized).cloned(),
+            locks::file_lock_level(constraint_index, &path_normalized).map(String::from),
+        ),
+        None => (None, None),
     };
 
     Ok(Json(ConstraintResponse {
-        path: path_normalized.to_string(),
+        path: path_normalized,
         constraints,
         lock_level,
     }))
@@ -0,0 +1,131 @@
+//! @acp:module "Context Handler"
+//! @acp:summary "Task-focused context bundle endpoint"
+//! @acp:domain daemon
+//! @acp:layer api
+
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::primer::{parse_tokenizer, SectionCost};
+use crate::api::{ApiError, ApiJson};
+use crate::context::{Bundle, ContextOptions};
+use crate::paths;
+use crate::primer::PrimerFormat;
+use crate::state::AppState;
+use crate::suggest;
+
+#[derive(Deserialize)]
+pub struct ContextRequest {
+    /// What the agent is about to do, matched against names and purposes
+    task: Option<String>,
+    /// Files the task starts from (paths resolve like `/files`)
+    #[serde(default)]
+    files: Vec<String>,
+    /// Symbols the task starts from
+    #[serde(default)]
+    symbols: Vec<String>,
+    /// Token budget for the bundle (default: 1500)
+    budget: Option<u32>,
+    /// Tokenizer encoding, model name or `heuristic` (default: the project's, else cl100k_base)
+    tokenizer: Option<String>,
+    /// Output format: text, markdown, xml or json (default: text)
+    #[serde(default)]
+    format: PrimerFormat,
+}
+
+#[derive(Serialize)]
+pub struct ContextResponse {
+    /// Requested token budget
+    budget: u32,
+    /// Tokens in `content` (for `json`, of the packed items), as counted by `tokenizer`
+    total_tokens: u32,
+    tokenizer: &'static str,
+    format: &'static str,
+    /// Included sections, in order
+    sections: Vec<&'static str>,
+    /// Files and symbols in the bundle, most relevant first
+    files: Vec<String>,
+    symbols: Vec<String>,
+    /// Rendered bundle (all formats but `json`)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    content: Option<String>,
+    /// Every candidate section and item with its token cost (`json` only)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    structure: Option<Vec<SectionCost>>,
+}
+
+/// POST /context - Build a context bundle for a task
+///
+/// Seeds are validated up front: unknown symbols return 404 with
+/// suggestions, and paths fail like `/files`.
+pub async fn build_context(
+    State(state): State<AppState>,
+    ApiJson(request): ApiJson<ContextRequest>,
+) -> Result<Json<ContextResponse>, ApiError> {
+    let task = request.task.filter(|task| !task.trim().is_empty());
+    if task.is_none() && request.files.is_empty() && request.symbols.is_empty() {
+        return Err(ApiError::bad_request(
+            "empty_context_request",
+            "give a task, files or symbols to build context for",
+        ));
+    }
+
+    let default_tokenizer = state.primer().await.tokenizer;
+    let tokenizer = parse_tokenizer(request.tokenizer.as_deref(), default_tokenizer)?;
+
+    let cache = state.cache_async().await;
+    let files = request
+        .files
+        .iter()
+        .map(|path| paths::resolve(state.project_root(), &cache.files, path))
+        .collect::<Result<Vec<_>, _>>()?;
+    for name in &request.symbols {
+        if !cache.symbols.contains_key(name) {
+            return Err(
+                ApiError::symbol_not_found(name).with_suggestions(suggest::closest(
+                    name,
+                    cache.symbols.keys().map(String::as_str),
+                )),
+            );
+        }
+    }
+
+    let options = ContextOptions {
+        task,
+        files,
+        symbols: request.symbols,
+        budget: request.budget.unwrap_or(1500),
+        tokenizer,
+        format: request.format,
+    };
+    let vars = state.vars().await;
+    let bundle = Bundle::build(&cache, vars.as_ref(), &options);
+
+    let (total_tokens, content) = match bundle.format {
+        PrimerFormat::Json => (bundle.packed_tokens(), None),
+        _ => {
+            let content = bundle.render();
+            (bundle.tokenizer.count(&content), Some(content))
+        }
+    };
+    let sections = bundle.included().map(|s| s.id).collect();
+    let structure = (bundle.format == PrimerFormat::Json).then(|| {
+        bundle
+            .sections
+            .iter()
+            .map(|section| SectionCost::new(section, bundle.format, bundle.tokenizer))
+            .collect()
+    });
+
+    Ok(Json(ContextResponse {
+        budget: bundle.budget,
+        total_tokens,
+        tokenizer: bundle.tokenizer.as_str(),
+        format: bundle.format.as_str(),
+        sections,
+        files: bundle.files,
+        symbols: bundle.symbols,
+        content,
+        structure,
+    }))
+}
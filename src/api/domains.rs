@@ -5,12 +5,13 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
 use serde::Serialize;
 
+use crate::api::ApiError;
 use crate::state::AppState;
+use crate::suggest;
 use acp::cache::DomainEntry;
 
 #[derive(Serialize)]
@@ -34,13 +35,13 @@ pub async fn list_domains(State(state): State<AppState>) -> Json<DomainListRespo
 pub async fn get_domain(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<Json<DomainEntry>, StatusCode> {
+) -> Result<Json<DomainEntry>, ApiError> {
     let cache = state.cache_async().await;
 
-    cache
-        .domains
-        .get(&name)
-        .cloned()
-        .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+    cache.domains.get(&name).cloned().map(Json).ok_or_else(|| {
+        ApiError::domain_not_found(&name).with_suggestions(suggest::closest(
+            &name,
+            cache.domains.keys().map(String::as_str),
+        ))
+    })
 }
@@ -0,0 +1,221 @@
+//! @acp:module "API Errors"
+//! @acp:summary "Structured JSON error responses shared by all handlers"
+//! @acp:domain daemon
+//! @acp:layer api
+//!
+//! Every error response has the same shape:
+//! `{"code": "symbol_not_found", "message": "...", "suggestions": [...]}`.
+//! `code` is stable and machine-readable; `message` is for humans.
+
+use axum::{
+    extract::{
+        rejection::{JsonRejection, QueryRejection},
+        FromRequest, FromRequestParts, OptionalFromRequest, Request,
+    },
+    http::{header, request::Parts, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::paths::PathError;
+
+/// Error returned by API handlers and middleware
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    status: StatusCode,
+    code: &'static str,
+    message: String,
+    suggestions: Vec<String>,
+}
+
+#[derive(Serialize)]
+struct ErrorBody<'a> {
+    code: &'a str,
+    message: &'a str,
+    suggestions: &'a [String],
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code,
+            message: message.into(),
+            suggestions: Vec::new(),
+        }
+    }
+
+    /// 400 for a malformed request
+    pub fn bad_request(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, code, message)
+    }
+
+    /// 404 for something that does not exist
+    pub fn not_found(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, code, message)
+    }
+
+    /// 409 for a request that conflicts with current state
+    pub fn conflict(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, code, message)
+    }
+
+    /// 503 for a temporary condition; clients should retry
+    pub fn unavailable(code: &'static str, message: impl Into<String>) -> Self {
+        Self::new(StatusCode::SERVICE_UNAVAILABLE, code, message)
+    }
+
+    /// Attach hints such as near-miss names or a command to run
+    pub fn with_suggestions<I, T>(mut self, suggestions: I) -> Self
+    where
+        I: IntoIterator<Item = T>,
+        T: Into<String>,
+    {
+        self.suggestions
+            .extend(suggestions.into_iter().map(Into::into));
+        self
+    }
+
+    /// No symbol with this name
+    pub fn symbol_not_found(name: &str) -> Self {
+        Self::not_found("symbol_not_found", format!("symbol '{}' not found", name))
+    }
+
+    /// No indexed file at this path
+    pub fn file_not_found(path: &str) -> Self {
+        Self::not_found("file_not_found", format!("file '{}' not found", path))
+    }
+
+    /// No domain with this name
+    pub fn domain_not_found(name: &str) -> Self {
+        Self::not_found("domain_not_found", format!("domain '{}' not found", name))
+    }
+
+    /// The cache has no call graph
+    pub fn graph_unavailable() -> Self {
+        Self::not_found("graph_unavailable", "cache has no call graph")
+            .with_suggestions(["Run 'acp index' to build the call graph"])
+    }
+
+    /// No vars file was loaded
+    pub fn vars_not_loaded() -> Self {
+        Self::not_found("vars_not_loaded", "no vars file loaded")
+            .with_suggestions(["Run 'acp index' to generate .acp/acp.vars.json"])
+    }
+
+    /// No variable with this name
+    pub fn variable_not_found(name: &str) -> Self {
+        Self::not_found(
+            "variable_not_found",
+            format!("variable '{}' not found", name),
+        )
+    }
+
+    /// No endpoint at this path
+    pub fn route_not_found(method: &str, path: &str) -> Self {
+        Self::not_found(
+            "route_not_found",
+            format!("no endpoint for {} {}", method, path),
+        )
+    }
+
+    /// The project's schemas are being reloaded from disk
+    pub fn reloading() -> Self {
+        Self::unavailable("reloading", "cache is being reloaded, retry shortly")
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorBody {
+            code: self.code,
+            message: &self.message,
+            suggestions: &self.suggestions,
+        });
+
+        match self.status {
+            StatusCode::UNAUTHORIZED => {
+                (self.status, [(header::WWW_AUTHENTICATE, "Bearer")], body).into_response()
+            }
+            StatusCode::SERVICE_UNAVAILABLE => {
+                (self.status, [(header::RETRY_AFTER, "1")], body).into_response()
+            }
+            status => (status, body).into_response(),
+        }
+    }
+}
+
+impl From<PathError> for ApiError {
+    fn from(err: PathError) -> Self {
+        let message = err.to_string();
+        match err {
+            PathError::Empty => Self::bad_request("empty_path", message),
+            PathError::OutsideProject(_) => Self::bad_request("path_outside_project", message),
+            PathError::Ambiguous { candidates, .. } => {
+                Self::conflict("ambiguous_path", message).with_suggestions(candidates)
+            }
+            PathError::NotFound { path, suggestions } => {
+                Self::file_not_found(&path).with_suggestions(suggestions)
+            }
+        }
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::bad_request("invalid_query", rejection.body_text())
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::new(rejection.status(), "invalid_body", rejection.body_text())
+    }
+}
+
+/// `Query` extractor that rejects bad parameters with an [`ApiError`]
+pub struct ApiQuery<T>(pub T);
+
+impl<T, S> FromRequestParts<S> for ApiQuery<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
+        let axum::extract::Query(value) =
+            axum::extract::Query::<T>::from_request_parts(parts, state).await?;
+        Ok(Self(value))
+    }
+}
+
+/// `Json` body extractor that rejects bad bodies with an [`ApiError`]
+pub struct ApiJson<T>(pub T);
+
+impl<T, S> FromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = <Json<T> as FromRequest<S>>::from_request(req, state).await?;
+        Ok(Self(value))
+    }
+}
+
+impl<T, S> OptionalFromRequest<S> for ApiJson<T>
+where
+    T: DeserializeOwned,
+    S: Send + Sync,
+{
+    type Rejection = ApiError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Option<Self>, Self::Rejection> {
+        let value = <Json<T> as OptionalFromRequest<S>>::from_request(req, state).await?;
+        Ok(value.map(|Json(value)| Self(value)))
+    }
+}
@@ -4,12 +4,14 @@
 //! @acp:layer api
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Path, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
 
+use crate::api::{ApiError, ApiQuery};
+use crate::paths;
+use crate::staleness::{StaleCheck, WithStale};
 use crate::state::AppState;
 use acp::cache::FileEntry;
 
@@ -27,14 +29,14 @@ pub struct FileQuery {
 
 #[derive(Serialize)]
 pub struct FileListResponse {
-    files: Vec<FileEntry>,
+    files: Vec<WithStale<FileEntry>>,
     total: usize,
 }
 
 /// GET /files - List files with optional filtering
 pub async fn list_files(
     State(state): State<AppState>,
-    Query(query): Query<FileQuery>,
+    ApiQuery(query): ApiQuery<FileQuery>,
 ) -> Json<FileListResponse> {
     let cache = state.cache_async().await;
 
@@ -71,33 +73,32 @@ pub async fn list_files(
         files.truncate(limit);
     }
 
+    let mut check = StaleCheck::new(state.project_root(), &cache);
+    let files = files
+        .into_iter()
+        .map(|file| WithStale {
+            stale: check.is_stale(&file.path),
+            entry: file,
+        })
+        .collect();
+
     Json(FileListResponse { files, total })
 }
 
 /// GET /files/*path - Get a specific file by path
+///
+/// Accepts project-relative, `./`-prefixed or absolute paths, or a unique
+/// suffix such as `auth/mod.rs`. Ambiguous suffixes return 409 listing
+/// every matching file.
 pub async fn get_file(
     State(state): State<AppState>,
     Path(path): Path<String>,
-) -> Result<Json<FileEntry>, StatusCode> {
+) -> Result<Json<WithStale<FileEntry>>, ApiError> {
     let cache = state.cache_async().await;
 
-    // Try exact match first
-    if let Some(file) = cache.files.get(&path) {
-        return Ok(Json(file.clone()));
-    }
-
-    // Try with leading slash removed
-    let path_no_slash = path.trim_start_matches('/');
-    if let Some(file) = cache.files.get(path_no_slash) {
-        return Ok(Json(file.clone()));
-    }
-
-    // Try partial match (ends with)
-    for (file_path, file) in &cache.files {
-        if file_path.ends_with(&path) || file_path.ends_with(path_no_slash) {
-            return Ok(Json(file.clone()));
-        }
-    }
-
-    Err(StatusCode::NOT_FOUND)
+    let resolved = paths::resolve(state.project_root(), &cache.files, &path)?;
+    let file = cache.files[&resolved].clone();
+    Ok(Json(
+        StaleCheck::new(state.project_root(), &cache).annotate(&resolved, file),
+    ))
 }
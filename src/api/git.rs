@@ -0,0 +1,76 @@
+//! @acp:module "Git Handler"
+//! @acp:summary "Changed files joined with cache metadata and constraints"
+//! @acp:domain daemon
+//! @acp:layer api
+
+use axum::{extract::State, http::StatusCode, Json};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiError, ApiQuery};
+use crate::changes::{self, ChangedFile, LockHit};
+use crate::git::{self, DiffTarget, GitError};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct ChangesQuery {
+    /// Ref to compare the working tree against (default: HEAD)
+    base: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ChangesResponse {
+    base: String,
+    files: Vec<ChangedFile>,
+    /// Locked files and symbols touched by the changes
+    locked: Vec<LockHit>,
+    /// Whether any frozen file or symbol was touched
+    touches_frozen: bool,
+}
+
+impl From<GitError> for ApiError {
+    fn from(err: GitError) -> Self {
+        let message = err.to_string();
+        match err {
+            GitError::NotARepository(_) => Self::not_found("not_a_git_repository", message),
+            GitError::InvalidRef(_) => Self::bad_request("invalid_ref", message),
+            GitError::UnknownRef(_) => Self::bad_request("unknown_ref", message),
+            GitError::Failed { .. } => {
+                Self::new(StatusCode::INTERNAL_SERVER_ERROR, "git_failed", message)
+            }
+        }
+    }
+}
+
+/// GET /git/changes - Files changed against a base ref, with constraints
+///
+/// Compares the working tree (staged, unstaged and untracked files) to
+/// `base`, so a pre-commit hook and a branch review both get one answer.
+pub async fn get_changes(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<ChangesQuery>,
+) -> Result<Json<ChangesResponse>, ApiError> {
+    let base = query.base.unwrap_or_else(|| "HEAD".to_string());
+    let root = state.project_root().to_path_buf();
+    let target = DiffTarget::WorkingTree { base: base.clone() };
+
+    let changes = tokio::task::spawn_blocking(move || git::diff(&root, &target))
+        .await
+        .map_err(|e| {
+            ApiError::new(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                "git_failed",
+                e.to_string(),
+            )
+        })??;
+
+    let cache = state.cache_async().await;
+    let files = changes::join(&cache, changes);
+    let locked = changes::lock_hits(&files);
+
+    Ok(Json(ChangesResponse {
+        base,
+        touches_frozen: locked.iter().any(|hit| hit.level == "frozen"),
+        files,
+        locked,
+    }))
+}
@@ -5,12 +5,13 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
 use serde::Serialize;
 
+use crate::api::ApiError;
 use crate::state::AppState;
+use crate::suggest;
 
 #[derive(Serialize)]
 pub struct GraphResponse {
@@ -23,18 +24,21 @@ pub struct GraphResponse {
 pub async fn get_callers(
     State(state): State<AppState>,
     Path(symbol): Path<String>,
-) -> Result<Json<GraphResponse>, StatusCode> {
+) -> Result<Json<GraphResponse>, ApiError> {
     let cache = state.cache_async().await;
 
+    let graph = cache
+        .graph
+        .as_ref()
+        .ok_or_else(ApiError::graph_unavailable)?;
+
     // Look up in reverse graph
-    if let Some(graph) = &cache.graph {
-        if let Some(callers) = graph.reverse.get(&symbol) {
-            return Ok(Json(GraphResponse {
-                symbol: symbol.clone(),
-                relationships: callers.clone(),
-                count: callers.len(),
-            }));
-        }
+    if let Some(callers) = graph.reverse.get(&symbol) {
+        return Ok(Json(GraphResponse {
+            symbol: symbol.clone(),
+            relationships: callers.clone(),
+            count: callers.len(),
+        }));
     }
 
     // Symbol exists but no callers
@@ -46,25 +50,35 @@ pub async fn get_callers(
         }));
     }
 
-    Err(StatusCode::NOT_FOUND)
+    // Graph nodes include external calls that have no symbol entry
+    let known = cache
+        .symbols
+        .keys()
+        .chain(graph.forward.keys())
+        .chain(graph.reverse.keys());
+    Err(ApiError::symbol_not_found(&symbol)
+        .with_suggestions(suggest::closest(&symbol, known.map(String::as_str))))
 }
 
 /// GET /callees/:symbol - Get callees of a symbol (forward graph)
 pub async fn get_callees(
     State(state): State<AppState>,
     Path(symbol): Path<String>,
-) -> Result<Json<GraphResponse>, StatusCode> {
+) -> Result<Json<GraphResponse>, ApiError> {
     let cache = state.cache_async().await;
 
+    let graph = cache
+        .graph
+        .as_ref()
+        .ok_or_else(ApiError::graph_unavailable)?;
+
     // Look up in forward graph
-    if let Some(graph) = &cache.graph {
-        if let Some(callees) = graph.forward.get(&symbol) {
-            return Ok(Json(GraphResponse {
-                symbol: symbol.clone(),
-                relationships: callees.clone(),
-                count: callees.len(),
-            }));
-        }
+    if let Some(callees) = graph.forward.get(&symbol) {
+        return Ok(Json(GraphResponse {
+            symbol: symbol.clone(),
+            relationships: callees.clone(),
+            count: callees.len(),
+        }));
     }
 
     // Symbol exists but no callees
@@ -76,5 +90,12 @@ pub async fn get_callees(
         }));
     }
 
-    Err(StatusCode::NOT_FOUND)
+    // Graph nodes include external calls that have no symbol entry
+    let known = cache
+        .symbols
+        .keys()
+        .chain(graph.forward.keys())
+        .chain(graph.reverse.keys());
+    Err(ApiError::symbol_not_found(&symbol)
+        .with_suggestions(suggest::closest(&symbol, known.map(String::as_str))))
 }
@@ -0,0 +1,97 @@
+//! @acp:module "Index Handler"
+//! @acp:summary "Background re-indexing and job status endpoints"
+//! @acp:domain daemon
+//! @acp:layer api
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tracing::error;
+
+use crate::api::{ApiError, ApiJson};
+use crate::indexer;
+use crate::jobs::{Job, JobInfo, JobStatus};
+use crate::state::AppState;
+
+#[derive(Deserialize)]
+pub struct IndexRequest {
+    /// Only re-index files under this path (default: whole project)
+    path: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct IndexJobResponse {
+    job_id: String,
+    status: JobStatus,
+    status_url: String,
+}
+
+impl From<&Job> for IndexJobResponse {
+    fn from(job: &Job) -> Self {
+        let info = job.snapshot();
+        Self {
+            status_url: format!("/jobs/{}", info.id),
+            job_id: info.id,
+            status: info.status,
+        }
+    }
+}
+
+/// POST /index - Start a background re-index of the project or a path
+///
+/// Returns 202 with the new job, or 409 naming the job already running.
+pub async fn start_index(
+    State(state): State<AppState>,
+    body: Option<ApiJson<IndexRequest>>,
+) -> Result<(StatusCode, Json<IndexJobResponse>), ApiError> {
+    let scope = body.and_then(|ApiJson(req)| req.path);
+
+    let job = state
+        .jobs()
+        .start("index", scope.clone())
+        .map_err(|running| {
+            ApiError::conflict(
+                "index_running",
+                format!("index job '{}' is already running", running.id()),
+            )
+            .with_suggestions([format!("Poll /jobs/{} for progress", running.id())])
+        })?;
+
+    let task_job = job.clone();
+    tokio::spawn(async move {
+        // Run the index in its own task so a panic still finishes the job
+        let index_job = task_job.clone();
+        let index =
+            tokio::spawn(
+                async move { indexer::reindex(&state, scope.as_deref(), &index_job).await },
+            );
+        match index.await {
+            Ok(Ok(summary)) => task_job.complete(summary),
+            Ok(Err(e)) => {
+                error!("Index job {} failed: {}", task_job.id(), e);
+                task_job.fail(e);
+            }
+            Err(e) => {
+                error!("Index job {} panicked: {}", task_job.id(), e);
+                task_job.fail(e);
+            }
+        }
+    });
+
+    Ok((StatusCode::ACCEPTED, Json((&job).into())))
+}
+
+/// GET /jobs/:id - Get progress and result of a background job
+pub async fn get_job(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<JobInfo>, ApiError> {
+    state
+        .jobs()
+        .get(&id)
+        .map(|job| Json(job.snapshot()))
+        .ok_or_else(|| ApiError::not_found("job_not_found", format!("job '{}' not found", id)))
+}
@@ -4,143 +4,132 @@
 //! @acp:layer api
 
 use axum::{
-    extract::{Query, State},
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
     Json,
 };
 use serde::{Deserialize, Serialize};
-use std::collections::BTreeMap;
 
+use crate::api::primer::parse_tokenizer;
+use crate::api::{ApiError, ApiQuery};
+use crate::map::{MapError, MapFormat, MapNode, MapOptions, ProjectMap};
+use crate::paths;
 use crate::state::AppState;
 
 #[derive(Deserialize)]
 pub struct MapQuery {
-    /// Maximum depth to traverse (default: 3)
+    /// Directory or file to root the map at (default: project root)
+    path: Option<String>,
+    /// Maximum depth to traverse below the root (default: 3)
     depth: Option<usize>,
-}
-
-#[derive(Serialize)]
-pub struct MapNode {
-    name: String,
-    #[serde(rename = "type")]
-    node_type: &'static str,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    children: Option<Vec<MapNode>>,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    symbols: Option<usize>,
+    /// Include inline annotations (TODOs, hacks) of each file
+    #[serde(default)]
+    inline: bool,
+    /// Output format: json, tree or markdown (default: json)
+    #[serde(default)]
+    format: MapFormat,
+    /// Token budget for text formats; deeper levels are dropped to fit
+    budget: Option<u32>,
+    /// Tokenizer for `budget` (default: the project's, else cl100k_base)
+    tokenizer: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct MapResponse {
+    /// Path the tree is rooted at
+    root: String,
     tree: MapNode,
     total_files: usize,
     total_dirs: usize,
 }
 
-/// GET /map - Get directory structure
-pub async fn get_map(
-    State(state): State<AppState>,
-    Query(query): Query<MapQuery>,
-) -> Json<MapResponse> {
-    let cache = state.cache_async().await;
-    let max_depth = query.depth.unwrap_or(3);
-
-    // Build tree from file paths
-    let mut dir_tree: BTreeMap<String, Vec<String>> = BTreeMap::new();
-    let mut file_symbols: BTreeMap<String, usize> = BTreeMap::new();
-
-    for (path, file_entry) in &cache.files {
-        // Count symbols per file
-        file_symbols.insert(path.clone(), file_entry.exports.len());
-
-        // Build directory hierarchy
-        let parts: Vec<&str> = path.split('/').collect();
-        for i in 0..parts.len() {
-            let dir_path = if i == 0 {
-                ".".to_string()
-            } else {
-                parts[..i].join("/")
-            };
-
-            let child = if i + 1 == parts.len() {
-                path.clone()
-            } else {
-                parts[..=i].join("/")
-            };
+#[derive(Serialize)]
+pub struct MapTextResponse {
+    root: String,
+    format: &'static str,
+    /// Levels shown below the root, after fitting the budget
+    depth: usize,
+    /// Lines were cut because even the root alone did not fit
+    truncated: bool,
+    /// Tokens in `content`, as counted by `tokenizer`
+    total_tokens: u32,
+    tokenizer: &'static str,
+    total_files: usize,
+    total_dirs: usize,
+    content: String,
+}
 
-            dir_tree.entry(dir_path).or_default().push(child);
+impl From<MapError> for ApiError {
+    fn from(err: MapError) -> Self {
+        let message = err.to_string();
+        match err {
+            MapError::NotFound { suggestions, .. } => {
+                Self::not_found("path_not_found", message).with_suggestions(suggestions)
+            }
         }
     }
+}
 
-    // Deduplicate children
-    for children in dir_tree.values_mut() {
-        children.sort();
-        children.dedup();
-    }
-
-    // Count directories
-    let total_dirs = dir_tree.len();
-
-    // Build tree structure
-    fn build_node(
-        name: &str,
-        path: &str,
-        dir_tree: &BTreeMap<String, Vec<String>>,
-        file_symbols: &BTreeMap<String, usize>,
-        depth: usize,
-        max_depth: usize,
-    ) -> MapNode {
-        if let Some(symbols) = file_symbols.get(path) {
-            // It's a file
-            MapNode {
-                name: name.to_string(),
-                node_type: "file",
-                children: None,
-                symbols: Some(*symbols),
-            }
-        } else if let Some(children) = dir_tree.get(path) {
-            // It's a directory
-            let child_nodes = if depth < max_depth {
-                Some(
-                    children
-                        .iter()
-                        .map(|child| {
-                            let child_name = child.rsplit('/').next().unwrap_or(child);
-                            build_node(
-                                child_name,
-                                child,
-                                dir_tree,
-                                file_symbols,
-                                depth + 1,
-                                max_depth,
-                            )
-                        })
-                        .collect(),
-                )
-            } else {
-                None
-            };
+/// GET /map - Get directory structure
+///
+/// `format=tree|markdown` returns the map as text, shown shallower until it
+/// fits `budget`.
+pub async fn get_map(
+    State(state): State<AppState>,
+    ApiQuery(query): ApiQuery<MapQuery>,
+) -> Result<Response, ApiError> {
+    let root = match query.path.as_deref() {
+        Some(path) => paths::normalize(state.project_root(), path)?,
+        None => String::new(),
+    };
+    let depth = query.depth.unwrap_or(3);
+    let options = MapOptions {
+        root,
+        depth,
+        inline: query.inline,
+    };
+    let tokenizer = parse_tokenizer(query.tokenizer.as_deref(), state.primer().await.tokenizer)?;
 
-            MapNode {
-                name: name.to_string(),
-                node_type: "directory",
-                children: child_nodes,
-                symbols: None,
-            }
-        } else {
-            MapNode {
-                name: name.to_string(),
-                node_type: "unknown",
-                children: None,
-                symbols: None,
-            }
-        }
+    let cache = state.cache_async().await;
+    let map = ProjectMap::build(&cache, &options)?;
+
+    if query.format == MapFormat::Json {
+        return Ok(Json(MapResponse {
+            root: map.tree.path.clone(),
+            tree: map.tree,
+            total_files: map.total_files,
+            total_dirs: map.total_dirs,
+        })
+        .into_response());
     }
 
-    let tree = build_node(".", ".", &dir_tree, &file_symbols, 0, max_depth);
+    drop(cache);
 
-    Json(MapResponse {
-        tree,
-        total_files: cache.files.len(),
-        total_dirs,
+    // Tokenizing large maps is CPU-bound
+    let (format, budget) = (query.format, query.budget);
+    let (map, rendered) = tokio::task::spawn_blocking(move || {
+        let rendered = map.render_within(format, depth, budget, tokenizer);
+        (map, rendered)
+    })
+    .await
+    .map_err(|e| {
+        ApiError::new(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            "map_failed",
+            e.to_string(),
+        )
+    })?;
+    Ok(Json(MapTextResponse {
+        root: map.tree.path.clone(),
+        format: format.as_str(),
+        depth: rendered.depth,
+        truncated: rendered.truncated,
+        total_tokens: rendered.tokens,
+        tokenizer: tokenizer.as_str(),
+        total_files: map.total_files,
+        total_dirs: map.total_dirs,
+        content: rendered.content,
     })
+    .into_response())
 }
@@ -6,12 +6,21 @@
 pub mod cache;
 pub mod config;
 pub mod constraints;
+pub mod context;
 pub mod domains;
+pub mod error;
 pub mod files;
+pub mod git;
 pub mod graph;
 pub mod health;
+pub mod index;
 pub mod map;
 pub mod primer;
+pub mod projects;
+pub mod source;
+pub mod staleness;
 pub mod stats;
 pub mod symbols;
 pub mod vars;
+
+pub use error::{ApiError, ApiJson, ApiQuery};
@@ -0,0 +1,127 @@
+//! @acp:module "Projects Handler"
+//! @acp:summary "Multi-project registry endpoints and per-project routing"
+//! @acp:domain daemon
+//! @acp:layer api
+
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Path, Request, State},
+    http::StatusCode,
+    response::Response,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use tower::ServiceExt;
+use tracing::error;
+
+use crate::api::{ApiError, ApiJson};
+use crate::projects::{ProjectError, ProjectInfo, ProjectRegistry};
+
+#[derive(Serialize)]
+pub struct ProjectListResponse {
+    projects: Vec<ProjectInfo>,
+    total: usize,
+}
+
+#[derive(Deserialize)]
+pub struct AddProjectRequest {
+    /// Project root directory
+    root: PathBuf,
+    /// Preferred project id (default: derived from the directory name)
+    id: Option<String>,
+}
+
+/// GET /projects - List registered projects
+pub async fn list_projects(State(registry): State<ProjectRegistry>) -> Json<ProjectListResponse> {
+    let projects = registry.list().await;
+    let total = projects.len();
+
+    Json(ProjectListResponse { projects, total })
+}
+
+/// POST /projects - Register a project root
+pub async fn add_project(
+    State(registry): State<ProjectRegistry>,
+    ApiJson(req): ApiJson<AddProjectRequest>,
+) -> Result<(StatusCode, Json<ProjectInfo>), ApiError> {
+    registry
+        .add(&req.root, req.id.as_deref())
+        .await
+        .map(|info| (StatusCode::CREATED, Json(info)))
+        .map_err(ApiError::from)
+}
+
+/// GET /projects/:id - Get a registered project
+pub async fn get_project(
+    State(registry): State<ProjectRegistry>,
+    Path(id): Path<String>,
+) -> Result<Json<ProjectInfo>, ApiError> {
+    registry.info(&id).await.map(Json).map_err(ApiError::from)
+}
+
+/// DELETE /projects/:id - Unregister a project
+pub async fn remove_project(
+    State(registry): State<ProjectRegistry>,
+    Path(id): Path<String>,
+) -> Result<Json<ProjectInfo>, ApiError> {
+    registry.remove(&id).await.map(Json).map_err(ApiError::from)
+}
+
+/// ANY /projects/:id/*rest - Route a request to a project's API
+pub async fn forward_to_project(
+    State(registry): State<ProjectRegistry>,
+    Path((id, _rest)): Path<(String, String)>,
+    req: Request,
+) -> Result<Response, ApiError> {
+    let router = registry.router(&id).await?;
+
+    // Strip the /projects/{id} prefix from the raw (still encoded) path: the
+    // id segment may be percent-encoded, so it need not equal `id`
+    let (parts, body) = req.into_parts();
+    let rest = parts
+        .uri
+        .path()
+        .strip_prefix("/projects/")
+        .and_then(|path| path.split_once('/'))
+        .map_or("", |(_, rest)| rest);
+    let path_and_query = match parts.uri.query() {
+        Some(query) => format!("/{}?{}", rest, query),
+        None => format!("/{}", rest),
+    };
+
+    // Rebuild the request without extensions: the outer route's path
+    // parameters would otherwise leak into the project's handlers
+    let mut forwarded = Request::builder()
+        .method(parts.method)
+        .uri(path_and_query)
+        .version(parts.version)
+        .body(body)
+        .map_err(|e| ApiError::bad_request("invalid_path", e.to_string()))?;
+    *forwarded.headers_mut() = parts.headers;
+
+    Ok(router
+        .oneshot(forwarded)
+        .await
+        .unwrap_or_else(|e| match e {}))
+}
+
+impl From<ProjectError> for ApiError {
+    fn from(err: ProjectError) -> Self {
+        let message = err.to_string();
+        match err {
+            ProjectError::NotFound(_) => ApiError::not_found("project_not_found", message)
+                .with_suggestions(["List registered projects with GET /projects"]),
+            ProjectError::InvalidRoot(_) => ApiError::bad_request("invalid_project_root", message),
+            ProjectError::AlreadyRegistered { .. } => {
+                ApiError::conflict("project_already_registered", message)
+            }
+            ProjectError::Pinned(_) => ApiError::conflict("project_pinned", message),
+            ProjectError::Load { .. } => {
+                error!("{}", message);
+                ApiError::unavailable("project_unavailable", message)
+                    .with_suggestions(["Run 'acp index' in the project root"])
+            }
+        }
+    }
+}
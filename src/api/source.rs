@@ -0,0 +1,282 @@
+//! @acp:module "Source Handler"
+//! @acp:summary "Source code retrieval for files and symbols"
+//! @acp:domain daemon
+//! @acp:layer api
+//!
+//! Only indexed files are served, so daemon state under `.acp/` (tokens,
+//! keys) is never readable. Paths are re-checked after resolving symlinks.
+
+use std::path::PathBuf;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::api::{ApiError, ApiQuery};
+use crate::paths;
+use crate::staleness;
+use crate::state::AppState;
+use crate::suggest;
+
+/// Largest file the daemon will read
+const MAX_SOURCE_BYTES: u64 = 1024 * 1024;
+
+/// Most context lines that may be requested around a range
+const MAX_CONTEXT_LINES: usize = 200;
+
+#[derive(Deserialize)]
+pub struct SourceQuery {
+    /// Line range `a-b` or single line `a` (1-based, inclusive)
+    lines: Option<String>,
+    /// Extra lines of context around the range (default: 0)
+    context: Option<usize>,
+}
+
+#[derive(Serialize)]
+pub struct SourceResponse {
+    path: String,
+    /// First and last line returned, including context
+    lines: [usize; 2],
+    /// Range that was asked for, before adding context
+    #[serde(skip_serializing_if = "Option::is_none")]
+    requested: Option<[usize; 2]>,
+    total_lines: usize,
+    /// The file was modified after it was last indexed
+    changed_since_index: bool,
+    content: String,
+}
+
+/// GET /source/*path - Read a file, or a line range of it
+pub async fn get_source(
+    State(state): State<AppState>,
+    Path(path): Path<String>,
+    ApiQuery(query): ApiQuery<SourceQuery>,
+) -> Result<Json<SourceResponse>, ApiError> {
+    let range = query.lines.as_deref().map(parse_range).transpose()?;
+
+    let (path, indexed_at) = {
+        let cache = state.cache_async().await;
+        let path = paths::resolve(state.project_root(), &cache.files, &path)?;
+        let indexed_at = staleness::indexed_at(&cache, &path);
+        (path, indexed_at)
+    };
+
+    read_source(&state, path, indexed_at, range, query.context).await
+}
+
+/// GET /symbols/:name/source - Read the source of a symbol
+pub async fn get_symbol_source(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+    ApiQuery(query): ApiQuery<SourceQuery>,
+) -> Result<Json<SourceResponse>, ApiError> {
+    let (path, indexed_at, range) = {
+        let cache = state.cache_async().await;
+        let symbol = cache.symbols.get(&name).ok_or_else(|| {
+            ApiError::symbol_not_found(&name).with_suggestions(suggest::closest(
+                &name,
+                cache.symbols.keys().map(String::as_str),
+            ))
+        })?;
+        let [start, end] = symbol.lines;
+        (
+            symbol.file.clone(),
+            staleness::indexed_at(&cache, &symbol.file),
+            (start.max(1), end.max(start).max(1)),
+        )
+    };
+
+    read_source(&state, path, indexed_at, Some(range), query.context).await
+}
+
+async fn read_source(
+    state: &AppState,
+    path: String,
+    indexed_at: DateTime<Utc>,
+    range: Option<(usize, usize)>,
+    context: Option<usize>,
+) -> Result<Json<SourceResponse>, ApiError> {
+    let full_path = contained_path(state, &path)?;
+
+    let metadata = tokio::fs::metadata(&full_path)
+        .await
+        .map_err(|_| source_missing(&path))?;
+    if metadata.len() > MAX_SOURCE_BYTES {
+        return Err(ApiError::new(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            "file_too_large",
+            format!(
+                "'{}' is {} bytes, more than the {} byte limit",
+                path,
+                metadata.len(),
+                MAX_SOURCE_BYTES
+            ),
+        ));
+    }
+    let changed_since_index = metadata
+        .modified()
+        .map(|modified| DateTime::<Utc>::from(modified) > indexed_at)
+        .unwrap_or(false);
+
+    let bytes = tokio::fs::read(&full_path)
+        .await
+        .map_err(|_| source_missing(&path))?;
+    let text = String::from_utf8_lossy(&bytes);
+    let all_lines: Vec<&str> = text.lines().collect();
+    let total_lines = all_lines.len();
+
+    let (start, end) = window(&path, range, context, total_lines)?;
+
+    let content = if total_lines == 0 {
+        String::new()
+    } else {
+        all_lines[start - 1..end].join("\n")
+    };
+
+    Ok(Json(SourceResponse {
+        path,
+        lines: [start, end],
+        requested: range.map(|(a, b)| [a, b]),
+        total_lines,
+        changed_since_index,
+        content,
+    }))
+}
+
+/// Absolute path of an indexed file, refusing anything that escapes the root
+fn contained_path(state: &AppState, path: &str) -> Result<PathBuf, ApiError> {
+    let outside = || {
+        ApiError::bad_request(
+            "path_outside_project",
+            format!("path '{}' is outside the project root", path),
+        )
+    };
+
+    let root = std::fs::canonicalize(state.project_root()).map_err(|_| outside())?;
+    let full_path = match std::fs::canonicalize(root.join(path)) {
+        Ok(full_path) => full_path,
+        Err(_) => return Err(source_missing(path)),
+    };
+
+    if full_path.starts_with(&root) {
+        Ok(full_path)
+    } else {
+        Err(outside())
+    }
+}
+
+fn source_missing(path: &str) -> ApiError {
+    ApiError::not_found(
+        "source_missing",
+        format!("'{}' is indexed but no longer exists on disk", path),
+    )
+    .with_suggestions(["Re-index with POST /index"])
+}
+
+/// Lines to return for `range` widened by `context`, clamped to the file
+fn window(
+    path: &str,
+    range: Option<(usize, usize)>,
+    context: Option<usize>,
+    total_lines: usize,
+) -> Result<(usize, usize), ApiError> {
+    let context = context.unwrap_or(0).min(MAX_CONTEXT_LINES);
+    match range {
+        Some((start, _)) if start > total_lines.max(1) => Err(ApiError::bad_request(
+            "invalid_range",
+            format!("'{}' has only {} lines", path, total_lines),
+        )),
+        Some((start, end)) => Ok((
+            start.saturating_sub(context).max(1),
+            end.saturating_add(context).min(total_lines),
+        )),
+        None => Ok((1, total_lines)),
+    }
+}
+
+/// Parse `a-b` or `a` into a 1-based inclusive range
+fn parse_range(lines: &str) -> Result<(usize, usize), ApiError> {
+    let invalid = || {
+        ApiError::bad_request(
+            "invalid_range",
+            format!("invalid line range '{}' (expected a-b or a)", lines),
+        )
+    };
+
+    let (start, end) = match lines.split_once('-') {
+        Some((start, end)) => (start.trim(), end.trim()),
+        None => (lines.trim(), lines.trim()),
+    };
+    let start: usize = start.parse().map_err(|_| invalid())?;
+    let end: usize = end.parse().map_err(|_| invalid())?;
+
+    if start == 0 || end < start {
+        return Err(invalid());
+    }
+    Ok((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_range_accepts_ranges_and_single_lines() {
+        assert_eq!(parse_range("3-7").unwrap(), (3, 7));
+        assert_eq!(parse_range(" 3 - 7 ").unwrap(), (3, 7));
+        assert_eq!(parse_range("5").unwrap(), (5, 5));
+        assert_eq!(parse_range("5-5").unwrap(), (5, 5));
+    }
+
+    #[test]
+    fn parse_range_rejects_malformed_ranges() {
+        for lines in [
+            "",
+            "0",
+            "0-3",
+            "7-3",
+            "-3",
+            "3-",
+            "a-b",
+            "1-2-3",
+            "1.5",
+            "99999999999999999999999",
+        ] {
+            assert!(
+                parse_range(lines).is_err(),
+                "'{}' should be rejected",
+                lines
+            );
+        }
+    }
+
+    #[test]
+    fn window_adds_context_within_the_file() {
+        assert_eq!(window("a.rs", Some((3, 5)), None, 10).unwrap(), (3, 5));
+        assert_eq!(window("a.rs", Some((3, 5)), Some(1), 10).unwrap(), (2, 6));
+        assert_eq!(window("a.rs", Some((2, 9)), Some(5), 10).unwrap(), (1, 10));
+        assert_eq!(
+            window("a.rs", Some((1, 1)), Some(usize::MAX), 10).unwrap(),
+            (1, 10)
+        );
+        assert_eq!(window("a.rs", None, Some(3), 10).unwrap(), (1, 10));
+    }
+
+    #[test]
+    fn window_clamps_ranges_past_the_end() {
+        assert_eq!(window("a.rs", Some((8, 50)), None, 10).unwrap(), (8, 10));
+        assert_eq!(window("a.rs", Some((10, 10)), None, 10).unwrap(), (10, 10));
+        assert!(window("a.rs", Some((11, 12)), None, 10).is_err());
+    }
+
+    #[test]
+    fn window_of_an_empty_file_is_empty() {
+        assert_eq!(window("a.rs", None, None, 0).unwrap(), (1, 0));
+        assert_eq!(window("a.rs", Some((1, 3)), None, 0).unwrap(), (1, 0));
+        assert!(window("a.rs", Some((2, 3)), None, 0).is_err());
+    }
+}
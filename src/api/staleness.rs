@@ -0,0 +1,31 @@
+//! @acp:module "Staleness Handler"
+//! @acp:summary "Cache drift report endpoint"
+//! @acp:domain daemon
+//! @acp:layer api
+
+use axum::{extract::State, Json};
+
+use crate::api::ApiError;
+use crate::indexer::{self, SourceMatcher};
+use crate::staleness::{self, IndexSnapshot, StalenessReport};
+use crate::state::AppState;
+
+/// GET /staleness - Report modified, deleted and unindexed files
+pub async fn get_staleness(
+    State(state): State<AppState>,
+) -> Result<Json<StalenessReport>, ApiError> {
+    let matcher = SourceMatcher::from_config(&*state.config().await);
+    let snapshot = IndexSnapshot::of(&*state.cache_async().await);
+    let root = indexer::canonical_root(&state);
+
+    tokio::task::spawn_blocking(move || staleness::report(root, snapshot, &matcher))
+        .await
+        .map(Json)
+        .map_err(|e| {
+            ApiError::new(
+                axum::http::StatusCode::INTERNAL_SERVER_ERROR,
+                "staleness_failed",
+                e.to_string(),
+            )
+        })
+}
@@ -4,13 +4,15 @@
 //! @acp:layer api
 
 use axum::{
-    extract::{Path, Query, State},
-    http::StatusCode,
+    extract::{Path, State},
     Json,
 };
 use serde::{Deserialize, Serialize};
 
+use crate::api::{ApiError, ApiQuery};
+use crate::staleness::{StaleCheck, WithStale};
 use crate::state::AppState;
+use crate::suggest;
 use acp::cache::SymbolEntry;
 
 #[derive(Deserialize)]
@@ -28,14 +30,14 @@ pub struct SymbolQuery {
 
 #[derive(Serialize)]
 pub struct SymbolListResponse {
-    symbols: Vec<SymbolEntry>,
+    symbols: Vec<WithStale<SymbolEntry>>,
     total: usize,
 }
 
 /// GET /symbols - List symbols with optional filtering
 pub async fn list_symbols(
     State(state): State<AppState>,
-    Query(query): Query<SymbolQuery>,
+    ApiQuery(query): ApiQuery<SymbolQuery>,
 ) -> Json<SymbolListResponse> {
     let cache = state.cache_async().await;
 
@@ -68,6 +70,15 @@ pub async fn list_symbols(
         symbols.truncate(limit);
     }
 
+    let mut check = StaleCheck::new(state.project_root(), &cache);
+    let symbols = symbols
+        .into_iter()
+        .map(|symbol| WithStale {
+            stale: check.is_stale(&symbol.file),
+            entry: symbol,
+        })
+        .collect();
+
     Json(SymbolListResponse { symbols, total })
 }
 
@@ -75,13 +86,18 @@ pub async fn list_symbols(
 pub async fn get_symbol(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<Json<SymbolEntry>, StatusCode> {
+) -> Result<Json<WithStale<SymbolEntry>>, ApiError> {
     let cache = state.cache_async().await;
 
-    cache
-        .symbols
-        .get(&name)
-        .cloned()
-        .map(Json)
-        .ok_or(StatusCode::NOT_FOUND)
+    let symbol = cache.symbols.get(&name).cloned().ok_or_else(|| {
+        ApiError::symbol_not_found(&name).with_suggestions(suggest::closest(
+            &name,
+            cache.symbols.keys().map(String::as_str),
+        ))
+    })?;
+
+    let file = symbol.file.clone();
+    Ok(Json(
+        StaleCheck::new(state.project_root(), &cache).annotate(&file, symbol),
+    ))
 }
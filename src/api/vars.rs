@@ -5,20 +5,20 @@
 
 use axum::{
     extract::{Path, State},
-    http::StatusCode,
     Json,
 };
 use serde::Serialize;
 
+use crate::api::ApiError;
 use crate::state::AppState;
 use acp::vars::VarsFile;
 
 /// GET /vars - Return vars JSON
-pub async fn get_vars(State(state): State<AppState>) -> Result<Json<VarsFile>, StatusCode> {
+pub async fn get_vars(State(state): State<AppState>) -> Result<Json<VarsFile>, ApiError> {
     let vars = state.vars().await;
     match vars.as_ref() {
         Some(v) => Ok(Json(v.clone())),
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(ApiError::vars_not_loaded()),
     }
 }
 
@@ -35,7 +35,7 @@ pub struct ExpandedVariable {
 pub async fn expand_variable(
     State(state): State<AppState>,
     Path(name): Path<String>,
-) -> Result<Json<ExpandedVariable>, StatusCode> {
+) -> Result<Json<ExpandedVariable>, ApiError> {
     let vars = state.vars().await;
 
     match vars.as_ref() {
@@ -53,9 +53,9 @@ pub async fn expand_variable(
                     lines: var.lines,
                 }))
             } else {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::variable_not_found(&name))
             }
         }
-        None => Err(StatusCode::NOT_FOUND),
+        None => Err(ApiError::vars_not_loaded()),
     }
 }
@@ -0,0 +1,257 @@
+//! @acp:module "Authentication"
+//! @acp:summary "Bearer-token authentication with scoped API keys"
+//! @acp:domain daemon
+//! @acp:layer transport
+//!
+//! A fresh admin token is generated at every start and written to
+//! `.acp/daemon.token` (mode 0600). Additional named keys with narrower
+//! scopes can be listed in `.acp/daemon.keys.json`.
+
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde::Deserialize;
+use tracing::{debug, info, warn};
+
+use crate::api::ApiError;
+use crate::settings::AuthSettings;
+
+/// Where the generated admin token is stored
+pub const TOKEN_FILE: &str = ".acp/daemon.token";
+
+/// Name of the generated admin key
+const ADMIN_KEY_NAME: &str = "daemon";
+
+/// Permission granted to an API key
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Query endpoints
+    #[serde(alias = "read-only")]
+    Read,
+    /// Trigger re-indexing (implies read)
+    Index,
+    /// Manage projects and everything else (implies index)
+    Admin,
+}
+
+/// A named API key
+#[derive(Debug, Clone, Deserialize)]
+pub struct ApiKey {
+    pub name: String,
+    pub token: String,
+    pub scopes: Vec<Scope>,
+}
+
+impl ApiKey {
+    fn allows(&self, required: Scope) -> bool {
+        self.scopes.iter().any(|scope| *scope >= required)
+    }
+}
+
+#[derive(Deserialize)]
+struct KeysFile {
+    keys: Vec<ApiKey>,
+}
+
+/// Validates bearer tokens against the known keys
+pub struct Authenticator {
+    keys: Vec<ApiKey>,
+}
+
+impl Authenticator {
+    /// Generate the admin token and load configured keys
+    ///
+    /// Returns `None` when authentication is disabled.
+    pub fn init(project_root: &Path, settings: &AuthSettings) -> anyhow::Result<Option<Arc<Self>>> {
+        if !settings.enabled {
+            warn!("Authentication is disabled; every client has full access");
+            return Ok(None);
+        }
+
+        let token = generate_token();
+        let token_path = project_root.join(TOKEN_FILE);
+        write_private(&token_path, &token)?;
+        info!("Admin token written to {}", token_path.display());
+
+        let mut keys = vec![ApiKey {
+            name: ADMIN_KEY_NAME.to_string(),
+            token,
+            scopes: vec![Scope::Admin],
+        }];
+        keys.extend(load_keys(&settings.keys_file)?);
+
+        Ok(Some(Arc::new(Self { keys })))
+    }
+
+    fn authenticate(&self, token: &str) -> Option<&ApiKey> {
+        // Check every key so timing does not reveal which one matched
+        self.keys.iter().fold(None, |found, key| {
+            if constant_time_eq(key.token.as_bytes(), token.as_bytes()) {
+                Some(key)
+            } else {
+                found
+            }
+        })
+    }
+}
+
+/// Middleware rejecting requests without a valid key for the route's scope
+pub async fn require_auth(
+    State(auth): State<Arc<Authenticator>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let Some(required) = required_scope(req.method(), req.uri().path()) else {
+        return next.run(req).await;
+    };
+
+    let token = req
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    match token.and_then(|t| auth.authenticate(t)) {
+        Some(key) if key.allows(required) => {
+            debug!("Authenticated '{}' for {:?}", key.name, required);
+            next.run(req).await
+        }
+        Some(key) => {
+            debug!("Key '{}' lacks {:?} scope", key.name, required);
+            ApiError::new(
+                StatusCode::FORBIDDEN,
+                "insufficient_scope",
+                format!("API key '{}' lacks the {:?} scope", key.name, required),
+            )
+            .into_response()
+        }
+        None => ApiError::new(
+            StatusCode::UNAUTHORIZED,
+            "unauthorized",
+            "missing or invalid bearer token",
+        )
+        .with_suggestions([format!(
+            "Send 'Authorization: Bearer <token>' with the token from {}",
+            TOKEN_FILE
+        )])
+        .into_response(),
+    }
+}
+
+/// Scope needed for a request, or `None` for public routes
+fn required_scope(method: &Method, path: &str) -> Option<Scope> {
+    // Requests routed to a project need the same scope as the root API
+    let api_path = match path.strip_prefix("/projects/") {
+        Some(rest) => match rest.split_once('/') {
+            Some((_, sub)) => format!("/{}", sub),
+            // Managing the registry itself
+            None if method == Method::GET => return Some(Scope::Read),
+            None => return Some(Scope::Admin),
+        },
+        None => path.to_string(),
+    };
+
+    match (method, api_path.as_str()) {
+        (_, "/health") => None,
+        (&Method::POST, "/index") => Some(Scope::Index),
+        // Builds a bundle from the cache without changing anything
+        (&Method::POST, "/context") => Some(Scope::Read),
+        (&Method::GET | &Method::HEAD, _) => Some(Scope::Read),
+        _ => Some(Scope::Admin),
+    }
+}
+
+fn load_keys(path: &Path) -> anyhow::Result<Vec<ApiKey>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    warn_if_readable_by_others(path);
+    let content = fs::read_to_string(path)?;
+    let file: KeysFile = serde_json::from_str(&content)
+        .map_err(|e| anyhow::anyhow!("invalid keys file {}: {}", path.display(), e))?;
+
+    for key in &file.keys {
+        if key.token.len() < 16 {
+            anyhow::bail!(
+                "API key '{}' is too short (minimum 16 characters)",
+                key.name
+            );
+        }
+    }
+
+    info!(
+        "Loaded {} API key(s) from {}",
+        file.keys.len(),
+        path.display()
+    );
+    Ok(file.keys)
+}
+
+fn generate_token() -> String {
+    let bytes: [u8; 32] = rand::random();
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+/// Write a file readable only by the current user
+pub(crate) fn write_private(path: &Path, content: &str) -> anyhow::Result<()> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    #[cfg(unix)]
+    {
+        use std::io::Write;
+        use std::os::unix::fs::{OpenOptionsExt, PermissionsExt};
+
+        let mut file = fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .mode(0o600)
+            .open(path)?;
+        // Tighten permissions on a file left over from an older run
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+        file.write_all(content.as_bytes())?;
+    }
+
+    #[cfg(not(unix))]
+    fs::write(path, content)?;
+
+    Ok(())
+}
+
+fn warn_if_readable_by_others(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+
+        if let Ok(metadata) = fs::metadata(path) {
+            if metadata.permissions().mode() & 0o077 != 0 {
+                warn!(
+                    "{} is accessible by other users; run chmod 600 on it",
+                    path.display()
+                );
+            }
+        }
+    }
+
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
@@ -0,0 +1,161 @@
+//! @acp:module "Changes"
+//! @acp:summary "Joins changed files with cache entries, constraints and symbols"
+//! @acp:domain daemon
+//! @acp:layer service
+
+use std::collections::{BTreeSet, HashMap};
+
+use acp::cache::{Cache, FileEntry, SymbolConstraint, SymbolEntry};
+use acp::constraints::Constraints;
+use serde::Serialize;
+
+use crate::git::FileChange;
+use crate::locks;
+use crate::paths;
+
+/// A changed file with what the cache knows about it
+#[derive(Serialize)]
+pub struct ChangedFile {
+    #[serde(flatten)]
+    pub change: FileChange,
+    pub indexed: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub file: Option<FileEntry>,
+    pub lock_level: Option<String>,
+    pub constraints: Option<Constraints>,
+    pub domains: Vec<String>,
+    /// Indexed symbols whose lines overlap a changed hunk
+    pub symbols: Vec<TouchedSymbol>,
+}
+
+/// An indexed symbol overlapping a changed hunk
+#[derive(Serialize)]
+pub struct TouchedSymbol {
+    pub name: String,
+    pub qualified_name: String,
+    pub lines: [usize; 2],
+    pub constraints: Option<SymbolConstraint>,
+}
+
+/// A locked file or symbol touched by a change
+#[derive(Serialize)]
+pub struct LockHit {
+    pub level: String,
+    pub path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbol: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lines: Option<[usize; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub directive: Option<String>,
+}
+
+/// Attach cache entries, constraints, domains and touched symbols to each change
+pub fn join(cache: &Cache, changes: Vec<FileChange>) -> Vec<ChangedFile> {
+    let mut symbols_by_file: HashMap<&str, Vec<&SymbolEntry>> = HashMap::new();
+    for symbol in cache.symbols.values() {
+        symbols_by_file
+            .entry(paths::strip_dot(&symbol.file))
+            .or_default()
+            .push(symbol);
+    }
+
+    changes
+        .into_iter()
+        .map(|change| {
+            // A renamed file is indexed under its old path until re-indexed
+            // Git paths are bare while `acp index` writes `./`-prefixed keys
+            let (key, entry) = [Some(&change.path), change.old_path.as_ref()]
+                .into_iter()
+                .flatten()
+                .find_map(|path| paths::cache_entry(&cache.files, path))
+                .map(|(key, entry)| (key.as_str(), Some(entry.clone())))
+                .unwrap_or((change.path.as_str(), None));
+
+            let (lock_level, constraints) = match &cache.constraints {
+                Some(index) => (
+                    locks::file_lock_level(index, key).map(String::from),
+                    paths::cache_entry(&index.by_file, key).map(|(_, c)| c.clone()),
+                ),
+                None => (None, None),
+            };
+
+            let mut domains: BTreeSet<String> = entry
+                .as_ref()
+                .map(|e| e.domains.iter().cloned().collect())
+                .unwrap_or_default();
+            domains.extend(
+                cache
+                    .domains
+                    .values()
+                    .filter(|domain| domain.files.iter().any(|f| paths::same_file(f, key)))
+                    .map(|domain| domain.name.clone()),
+            );
+
+            let mut symbols: Vec<TouchedSymbol> = symbols_by_file
+                .get(paths::strip_dot(key))
+                .into_iter()
+                .flatten()
+                .filter(|symbol| change.touches(symbol.lines))
+                .map(|symbol| TouchedSymbol {
+                    name: symbol.name.clone(),
+                    qualified_name: symbol.qualified_name.clone(),
+                    lines: symbol.lines,
+                    constraints: symbol.constraints.clone(),
+                })
+                .collect();
+            symbols.sort_by(|a, b| a.lines.cmp(&b.lines).then_with(|| a.name.cmp(&b.name)));
+
+            ChangedFile {
+                indexed: entry.is_some(),
+                file: entry,
+                lock_level,
+                constraints,
+                domains: domains.into_iter().collect(),
+                symbols,
+                change,
+            }
+        })
+        .collect()
+}
+
+/// Locked files and symbols among the changes, frozen first
+pub fn lock_hits(files: &[ChangedFile]) -> Vec<LockHit> {
+    let mut hits = Vec::new();
+    for file in files {
+        if let Some(level) = &file.lock_level {
+            hits.push(LockHit {
+                level: level.clone(),
+                path: file.change.path.clone(),
+                symbol: None,
+                lines: None,
+                directive: file.constraints.as_ref().and_then(|c| c.directive.clone()),
+            });
+        }
+        for symbol in &file.symbols {
+            if let Some(constraint) = &symbol.constraints {
+                hits.push(LockHit {
+                    level: constraint.level.clone(),
+                    path: file.change.path.clone(),
+                    symbol: Some(symbol.qualified_name.clone()),
+                    lines: Some(symbol.lines),
+                    directive: Some(constraint.directive.clone()),
+                });
+            }
+        }
+    }
+
+    let rank = |level: &str| {
+        locks::LOCK_LEVELS
+            .iter()
+            .position(|l| *l == level)
+            .unwrap_or(locks::LOCK_LEVELS.len())
+    };
+    hits.sort_by(|a, b| {
+        rank(&a.level)
+            .cmp(&rank(&b.level))
+            .then_with(|| a.path.cmp(&b.path))
+            .then_with(|| a.symbol.cmp(&b.symbol))
+    });
+    hits
+}
@@ -0,0 +1,471 @@
+//! @acp:module "Context"
+//! @acp:summary "Task-focused context bundles packed into a token budget"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! A bundle grows from seed files and symbols along the call graph. Without
+//! seeds, the symbols whose names and purposes best match the task text
+//! seed it instead. Candidates are ranked by graph distance, then lexical
+//! match, and packed with the primer's token accounting, so an agent gets
+//! purposes, signatures, constraints, neighbours and variables in one call.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, VecDeque};
+
+use acp::cache::{Cache, FileEntry, SymbolEntry};
+use acp::vars::VarsFile;
+
+use crate::locks;
+use crate::primer::tokens::TokenCounter;
+use crate::primer::{self, Item, PrimerFormat, Section};
+use crate::suggest;
+
+/// Call-graph hops explored from each seed
+const MAX_DISTANCE: usize = 2;
+
+/// Symbols seeded from the task text when no seeds are given
+const TASK_SEEDS: usize = 3;
+
+/// Candidates considered for packing
+const MAX_FILES: usize = 25;
+const MAX_SYMBOLS: usize = 50;
+
+/// Callers and callees listed per symbol
+const MAX_NEIGHBOURS: usize = 5;
+
+/// Words too common to say anything about relevance
+const STOP_WORDS: &[&str] = &[
+    "the", "and", "for", "with", "that", "this", "from", "into", "when", "should", "add", "fix",
+    "make", "use", "not", "are", "can", "all", "new",
+];
+
+/// What to build a bundle for; `files` are indexed paths, `symbols` known names
+pub struct ContextOptions {
+    pub task: Option<String>,
+    pub files: Vec<String>,
+    pub symbols: Vec<String>,
+    pub budget: u32,
+    pub tokenizer: TokenCounter,
+    pub format: PrimerFormat,
+}
+
+/// A context bundle packed into a token budget
+pub struct Bundle {
+    pub budget: u32,
+    pub tokenizer: TokenCounter,
+    pub format: PrimerFormat,
+    /// Candidate sections, items flagged if packed
+    pub sections: Vec<Section>,
+    /// Packed files and symbols, most relevant first
+    pub files: Vec<String>,
+    pub symbols: Vec<String>,
+}
+
+/// A file or symbol with its relevance
+struct Candidate<'a> {
+    kind: Kind<'a>,
+    /// Hops from the nearest seed, if reachable
+    distance: Option<usize>,
+    score: f64,
+}
+
+enum Kind<'a> {
+    File(&'a str, &'a FileEntry),
+    Symbol(&'a str, &'a SymbolEntry),
+}
+
+impl Candidate<'_> {
+    fn name(&self) -> &str {
+        match self.kind {
+            Kind::File(path, _) => path,
+            Kind::Symbol(name, _) => name,
+        }
+    }
+
+    fn path(&self) -> &str {
+        match self.kind {
+            Kind::File(path, _) => path,
+            Kind::Symbol(_, symbol) => &symbol.file,
+        }
+    }
+}
+
+impl Bundle {
+    pub fn build(cache: &Cache, vars: Option<&VarsFile>, options: &ContextOptions) -> Self {
+        let terms = options.task.as_deref().map(task_terms).unwrap_or_default();
+        let candidates = rank(cache, options, &terms);
+
+        let mut constraints = Section::new("constraints", Some("Constraints"), 0);
+        let mut files = Section::new("files", Some("Files"), 1);
+        let mut symbols = Section::new("symbols", Some("Symbols"), 2);
+        // Names behind each item of `files` and `symbols`
+        let mut file_names = Vec::new();
+        let mut symbol_names = Vec::new();
+
+        for (rank, candidate) in candidates.iter().enumerate() {
+            let rank = rank as u32;
+            let seed = candidate.distance == Some(0);
+            if let Some(text) = constraint_text(cache, candidate) {
+                let item = Item::new(text).ranked(rank);
+                // Agents must see the locks on what they were asked to touch
+                constraints
+                    .items
+                    .push(if seed { item.required() } else { item });
+            }
+            match candidate.kind {
+                Kind::File(path, file) => {
+                    files
+                        .items
+                        .push(Item::new(file_text(path, file)).ranked(rank));
+                    file_names.push(path.to_string());
+                }
+                Kind::Symbol(name, symbol) => {
+                    symbols
+                        .items
+                        .push(Item::new(symbol_text(cache, symbol)).ranked(rank));
+                    symbol_names.push(name.to_string());
+                }
+            }
+        }
+
+        let variables = variables(vars, options.task.as_deref(), &candidates);
+
+        let format = options.format;
+        let counter = options.tokenizer;
+        let mut sections: Vec<Section> = [constraints, files, symbols, variables]
+            .into_iter()
+            .map(|section| primer::cost(section, format, counter))
+            .collect();
+        primer::pack(&mut sections, options.budget, format, counter);
+
+        let packed = |section: &Section, names: Vec<String>| {
+            section
+                .items
+                .iter()
+                .zip(names)
+                .filter(|(item, _)| item.included)
+                .map(|(_, name)| name)
+                .collect()
+        };
+        Self {
+            budget: options.budget,
+            tokenizer: counter,
+            format,
+            files: packed(&sections[1], file_names),
+            symbols: packed(&sections[2], symbol_names),
+            sections,
+        }
+    }
+
+    /// Rendered bundle; JSON callers render items themselves, so it gets text
+    pub fn render(&self) -> String {
+        primer::render(&self.sections, self.format)
+    }
+
+    /// Tokens charged while packing
+    pub fn packed_tokens(&self) -> u32 {
+        primer::packed_tokens(&self.sections, self.format, self.tokenizer)
+    }
+
+    /// Sections with at least one packed item
+    pub fn included(&self) -> impl Iterator<Item = &Section> {
+        primer::included(&self.sections)
+    }
+}
+
+/// Files and symbols relevant to the request, most relevant first
+fn rank<'a>(
+    cache: &'a Cache,
+    options: &ContextOptions,
+    terms: &BTreeSet<String>,
+) -> Vec<Candidate<'a>> {
+    let lexical_symbols: HashMap<&str, f64> = cache
+        .symbols
+        .iter()
+        .map(|(name, symbol)| {
+            let named = suggest::tokens(name);
+            let described = describe(symbol.purpose.as_deref(), symbol.summary.as_deref());
+            (name.as_str(), lexical(terms, &named, &described))
+        })
+        .collect();
+
+    // Seeds: requested symbols and everything in requested files, else the
+    // best lexical matches
+    let mut seeds: BTreeSet<&str> = options.symbols.iter().map(String::as_str).collect();
+    seeds.extend(
+        cache
+            .symbols
+            .iter()
+            .filter(|(_, symbol)| options.files.contains(&symbol.file))
+            .map(|(name, _)| name.as_str()),
+    );
+    if seeds.is_empty() && options.files.is_empty() {
+        let mut matches: Vec<(&str, f64)> = lexical_symbols
+            .iter()
+            .filter(|(_, score)| **score > 0.0)
+            .map(|(name, score)| (*name, *score))
+            .collect();
+        matches.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+        seeds.extend(matches.into_iter().take(TASK_SEEDS).map(|(name, _)| name));
+    }
+
+    let distances = distances(cache, &seeds);
+    let proximity = |distance: Option<usize>| distance.map_or(0.0, |d| 2.0 / (1 + d) as f64);
+
+    let mut symbols: Vec<Candidate> = cache
+        .symbols
+        .iter()
+        .filter_map(|(name, symbol)| {
+            let distance = distances.get(name.as_str()).copied();
+            let score = proximity(distance) + lexical_symbols[name.as_str()];
+            (score > 0.0).then_some(Candidate {
+                kind: Kind::Symbol(name, symbol),
+                distance,
+                score,
+            })
+        })
+        .collect();
+    sort(&mut symbols);
+    symbols.truncate(MAX_SYMBOLS);
+
+    // Files score as their best symbol, or on their own path and purpose
+    let mut best: HashMap<&str, (Option<usize>, f64)> = HashMap::new();
+    for symbol in &symbols {
+        let entry = best.entry(symbol.path()).or_insert((None, 0.0));
+        entry.0 = match (entry.0, symbol.distance) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        entry.1 = entry.1.max(symbol.score);
+    }
+    let mut files: Vec<Candidate> = cache
+        .files
+        .iter()
+        .filter_map(|(path, file)| {
+            let (distance, from_symbols) = if options.files.contains(path) {
+                (Some(0), proximity(Some(0)))
+            } else {
+                best.get(path.as_str()).copied().unwrap_or((None, 0.0))
+            };
+            let own = lexical(
+                terms,
+                &suggest::tokens(path),
+                &describe(file.purpose.as_deref(), file.summary.as_deref()),
+            );
+            let score = from_symbols.max(own);
+            (score > 0.0).then_some(Candidate {
+                kind: Kind::File(path, file),
+                distance,
+                score,
+            })
+        })
+        .collect();
+    sort(&mut files);
+    files.truncate(MAX_FILES);
+
+    let mut candidates: Vec<Candidate> = files.into_iter().chain(symbols).collect();
+    sort(&mut candidates);
+    candidates
+}
+
+/// Closest to a seed first, then best scoring, then by name
+fn sort(candidates: &mut [Candidate]) {
+    candidates.sort_by(|a, b| {
+        a.distance
+            .unwrap_or(usize::MAX)
+            .cmp(&b.distance.unwrap_or(usize::MAX))
+            .then(b.score.total_cmp(&a.score))
+            .then_with(|| a.name().cmp(b.name()))
+    });
+}
+
+/// Hops from the nearest seed along calls in either direction
+fn distances<'a>(cache: &'a Cache, seeds: &BTreeSet<&'a str>) -> HashMap<&'a str, usize> {
+    let mut distances: HashMap<&str, usize> = seeds.iter().map(|seed| (*seed, 0)).collect();
+    let Some(graph) = &cache.graph else {
+        return distances;
+    };
+
+    let mut queue: VecDeque<&str> = seeds.iter().copied().collect();
+    while let Some(name) = queue.pop_front() {
+        let distance = distances[name];
+        if distance == MAX_DISTANCE {
+            continue;
+        }
+        let neighbours = graph
+            .forward
+            .get(name)
+            .into_iter()
+            .chain(graph.reverse.get(name))
+            .flatten();
+        for neighbour in neighbours {
+            if !distances.contains_key(neighbour.as_str()) {
+                distances.insert(neighbour, distance + 1);
+                queue.push_back(neighbour);
+            }
+        }
+    }
+    distances
+}
+
+/// Distinctive lowercase words of the task
+fn task_terms(task: &str) -> BTreeSet<String> {
+    suggest::tokens(task)
+        .into_iter()
+        .filter(|term| term.len() >= 3 && !STOP_WORDS.contains(&term.as_str()))
+        .collect()
+}
+
+fn describe(purpose: Option<&str>, summary: Option<&str>) -> BTreeSet<String> {
+    purpose
+        .into_iter()
+        .chain(summary)
+        .flat_map(suggest::tokens)
+        .collect()
+}
+
+/// Share of task terms matched, counting name matches double, in `0.0..=1.0`
+fn lexical(
+    terms: &BTreeSet<String>,
+    named: &BTreeSet<String>,
+    described: &BTreeSet<String>,
+) -> f64 {
+    if terms.is_empty() {
+        return 0.0;
+    }
+    let hits: usize = terms
+        .iter()
+        .map(|term| {
+            if named.contains(term) {
+                2
+            } else {
+                usize::from(described.contains(term))
+            }
+        })
+        .sum();
+    hits as f64 / (2 * terms.len()) as f64
+}
+
+fn constraint_text(cache: &Cache, candidate: &Candidate) -> Option<String> {
+    let (level, directive) = match candidate.kind {
+        Kind::File(path, _) => {
+            let index = cache.constraints.as_ref()?;
+            let level = locks::file_lock_level(index, path)?;
+            let directive = index.by_file.get(path).and_then(|c| c.directive.clone());
+            (level.to_string(), directive)
+        }
+        Kind::Symbol(_, symbol) => {
+            let constraints = symbol.constraints.as_ref()?;
+            (
+                constraints.level.clone(),
+                Some(constraints.directive.clone()),
+            )
+        }
+    };
+    Some(match directive.filter(|d| !d.is_empty()) {
+        Some(directive) => format!("  - {}: {} ({})", candidate.name(), level, directive),
+        None => format!("  - {}: {}", candidate.name(), level),
+    })
+}
+
+fn file_text(path: &str, file: &FileEntry) -> String {
+    let mut text = format!("  - {}", path);
+    if let Some(purpose) = file.purpose.as_deref().or(file.summary.as_deref()) {
+        text.push_str(&format!(": {}", purpose));
+    }
+    if !file.domains.is_empty() {
+        text.push_str(&format!(" [{}]", file.domains.join(", ")));
+    }
+    text
+}
+
+fn symbol_text(cache: &Cache, symbol: &SymbolEntry) -> String {
+    let mut text = format!(
+        "  - {} ({}:{}-{})",
+        symbol.name, symbol.file, symbol.lines[0], symbol.lines[1]
+    );
+    if let Some(signature) = &symbol.signature {
+        text.push_str(&format!("\n    {}", signature));
+    }
+    if let Some(purpose) = symbol.purpose.as_deref().or(symbol.summary.as_deref()) {
+        text.push_str(&format!("\n    {}", purpose));
+    }
+    if let Some(graph) = &cache.graph {
+        for (label, names) in [
+            ("callers", graph.reverse.get(&symbol.name)),
+            ("callees", graph.forward.get(&symbol.name)),
+        ] {
+            let names: BTreeSet<&String> = names.into_iter().flatten().collect();
+            if names.is_empty() {
+                continue;
+            }
+            let list: Vec<&str> = names
+                .iter()
+                .take(MAX_NEIGHBOURS)
+                .map(|n| n.as_str())
+                .collect();
+            let more = names.len().saturating_sub(MAX_NEIGHBOURS);
+            let more = if more > 0 {
+                format!(", +{} more", more)
+            } else {
+                String::new()
+            };
+            text.push_str(&format!("\n    {}: {}{}", label, list.join(", "), more));
+        }
+    }
+    text
+}
+
+/// Variables named in the task (`$NAME`), then those sourced from candidates
+fn variables(vars: Option<&VarsFile>, task: Option<&str>, candidates: &[Candidate]) -> Section {
+    let mut section = Section::new("variables", Some("Variables"), 3);
+    let Some(vars) = vars else {
+        return section;
+    };
+
+    // Rank 0 for variables the task names, else the rank of their source
+    let mentioned = task.map(mentioned_vars).unwrap_or_default();
+    let mut chosen: BTreeMap<&str, u32> = BTreeMap::new();
+    for (name, var) in &vars.variables {
+        if mentioned.contains(&name.trim_start_matches('$')) {
+            chosen.insert(name, 0);
+            continue;
+        }
+        let Some(source) = var.source.as_deref() else {
+            continue;
+        };
+        let rank = candidates
+            .iter()
+            .position(|c| c.name() == source || c.path() == source);
+        if let Some(rank) = rank {
+            chosen.entry(name).or_insert(rank as u32);
+        }
+    }
+
+    let mut chosen: Vec<(&str, u32)> = chosen.into_iter().collect();
+    chosen.sort_by_key(|&(name, rank)| (rank, name));
+    section.items = chosen
+        .into_iter()
+        .map(|(name, rank)| {
+            let var = &vars.variables[name];
+            let mut text = format!("  - ${} = {}", name.trim_start_matches('$'), var.value);
+            if let Some(description) = &var.description {
+                text.push_str(&format!(" ({})", description));
+            }
+            Item::new(text).ranked(rank)
+        })
+        .collect();
+    section
+}
+
+/// `$NAME` references in `text`, without the `$`
+fn mentioned_vars(text: &str) -> Vec<&str> {
+    text.split('$')
+        .skip(1)
+        .filter_map(|rest| {
+            let end = rest
+                .find(|c: char| !c.is_alphanumeric() && c != '_')
+                .unwrap_or(rest.len());
+            (end > 0).then(|| &rest[..end])
+        })
+        .collect()
+}
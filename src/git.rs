@@ -0,0 +1,475 @@
+//! @acp:module "Git"
+//! @acp:summary "Changed files and hunks read from the local git repository"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Shells out to `git` (run in the project root with `--relative`, so
+//! paths match cache keys) and parses zero-context diffs into per-file
+//! line ranges.
+
+use std::path::Path;
+use std::process::Command;
+
+use serde::Serialize;
+
+/// Errors from git operations
+#[derive(Debug, thiserror::Error)]
+pub enum GitError {
+    #[error("{0} is not inside a git repository")]
+    NotARepository(String),
+    #[error("invalid ref '{0}'")]
+    InvalidRef(String),
+    #[error("unknown ref '{0}'")]
+    UnknownRef(String),
+    #[error("git {command} failed: {message}")]
+    Failed { command: String, message: String },
+}
+
+/// What to compare
+#[derive(Debug, Clone)]
+pub enum DiffTarget {
+    /// Working tree (staged and unstaged changes, plus untracked files) against a ref
+    WorkingTree { base: String },
+    /// Staged changes against `HEAD`, as a pre-commit hook sees them
+    Staged,
+}
+
+/// How a file changed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+    Renamed,
+}
+
+/// One changed file and the line ranges its hunks touch
+#[derive(Debug, Clone, Serialize)]
+pub struct FileChange {
+    pub path: String,
+    pub status: ChangeStatus,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old_path: Option<String>,
+    /// Changed line ranges in the new file (1-based, inclusive)
+    pub hunks: Vec<[usize; 2]>,
+}
+
+impl FileChange {
+    /// Whether a `[start, end]` line range overlaps any hunk
+    ///
+    /// Every line of an added or deleted file counts as touched.
+    pub fn touches(&self, lines: [usize; 2]) -> bool {
+        match self.status {
+            ChangeStatus::Added | ChangeStatus::Deleted => true,
+            _ => self
+                .hunks
+                .iter()
+                .any(|[start, end]| *start <= lines[1] && lines[0] <= *end),
+        }
+    }
+}
+
+/// List changed files under `project_root`
+pub fn diff(project_root: &Path, target: &DiffTarget) -> Result<Vec<FileChange>, GitError> {
+    git(project_root, &["rev-parse", "--is-inside-work-tree"])
+        .map_err(|_| GitError::NotARepository(project_root.display().to_string()))?;
+
+    let mut args = vec![
+        "-c",
+        "core.quotepath=off",
+        "diff",
+        "--no-color",
+        "--no-ext-diff",
+        "--unified=0",
+        "--find-renames",
+        "--relative",
+    ];
+    match target {
+        DiffTarget::WorkingTree { base } => {
+            verify_ref(project_root, base)?;
+            args.push(base);
+        }
+        DiffTarget::Staged => args.push("--cached"),
+    }
+    args.push("--");
+
+    let mut changes = parse_diff(&git(project_root, &args)?);
+
+    // Untracked files are new to the branch too, but are never staged
+    if let DiffTarget::WorkingTree { .. } = target {
+        let untracked = git(
+            project_root,
+            &["ls-files", "--others", "--exclude-standard", "-z"],
+        )?;
+        changes.extend(
+            untracked
+                .split('\0')
+                .filter(|path| !path.is_empty())
+                .map(|path| FileChange {
+                    path: path.to_string(),
+                    status: ChangeStatus::Added,
+                    old_path: None,
+                    hunks: Vec::new(),
+                }),
+        );
+    }
+
+    changes.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(changes)
+}
+
+/// Reject option-like refs and refs that do not name a commit
+fn verify_ref(project_root: &Path, base: &str) -> Result<(), GitError> {
+    if base.is_empty() || base.starts_with('-') {
+        return Err(GitError::InvalidRef(base.to_string()));
+    }
+    git(
+        project_root,
+        &[
+            "rev-parse",
+            "--verify",
+            "--quiet",
+            &format!("{}^{{commit}}", base),
+        ],
+    )
+    .map(|_| ())
+    .map_err(|_| GitError::UnknownRef(base.to_string()))
+}
+
+fn git(project_root: &Path, args: &[&str]) -> Result<String, GitError> {
+    let failed = |message: String| GitError::Failed {
+        command: args
+            .iter()
+            .find(|a| !a.starts_with('-'))
+            .copied()
+            .unwrap_or("")
+            .to_string(),
+        message,
+    };
+
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(project_root)
+        .output()
+        .map_err(|e| failed(e.to_string()))?;
+
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(failed(
+            String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        ))
+    }
+}
+
+/// Parse `git diff --unified=0` output
+fn parse_diff(output: &str) -> Vec<FileChange> {
+    let mut changes = Vec::new();
+    let mut current: Option<FileChange> = None;
+    // Past the first `@@`, lines are content even when they look like headers
+    let mut in_hunks = false;
+
+    for line in output.lines() {
+        if let Some(header) = line.strip_prefix("diff --git ") {
+            changes.extend(current.take());
+            in_hunks = false;
+            current = Some(FileChange {
+                path: header_path(header),
+                status: ChangeStatus::Modified,
+                old_path: None,
+                hunks: Vec::new(),
+            });
+            continue;
+        }
+        let Some(change) = current.as_mut() else {
+            continue;
+        };
+
+        if line.starts_with("@@") {
+            in_hunks = true;
+            change.hunks.extend(parse_hunk(line));
+        } else if in_hunks {
+            continue;
+        } else if line.starts_with("new file mode") {
+            change.status = ChangeStatus::Added;
+        } else if line.starts_with("deleted file mode") {
+            change.status = ChangeStatus::Deleted;
+        } else if let Some(from) = line.strip_prefix("rename from ") {
+            change.status = ChangeStatus::Renamed;
+            change.old_path = Some(unquote(from));
+        } else if let Some(to) = line.strip_prefix("rename to ") {
+            change.path = unquote(to);
+        } else if let Some(old) = line.strip_prefix("--- ") {
+            if change.status == ChangeStatus::Deleted {
+                if let Some(old) = unquote(old).strip_prefix("a/") {
+                    change.path = old.to_string();
+                }
+            }
+        } else if let Some(new) = line.strip_prefix("+++ ") {
+            if let Some(new) = unquote(new).strip_prefix("b/") {
+                change.path = new.to_string();
+            }
+        }
+    }
+    changes.extend(current);
+    changes
+}
+
+/// Path from `a/<path> b/<path>` when both sides are equal (binary files, mode changes)
+fn header_path(header: &str) -> String {
+    // Quoted paths (`"a/x y" "b/x y"`) end at the first unescaped quote
+    if let Some(end) = closing_quote(header) {
+        let old = unquote(&header[..=end]);
+        return old.strip_prefix("a/").map(str::to_string).unwrap_or(old);
+    }
+
+    let header = unquote(header);
+    let half = header.len().saturating_sub(1) / 2;
+    header
+        .get(..half)
+        .and_then(|a| a.strip_prefix("a/"))
+        .unwrap_or(&header)
+        .to_string()
+}
+
+/// Byte index of the quote closing a path that starts with `"`
+fn closing_quote(quoted: &str) -> Option<usize> {
+    let mut escaped = false;
+    for (i, c) in quoted.strip_prefix('"')?.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' => escaped = true,
+            '"' => return Some(i + 1),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// New-side range of a `@@ -a,b +c,d @@` header
+fn parse_hunk(line: &str) -> Option<[usize; 2]> {
+    let new = line
+        .split_whitespace()
+        .find_map(|part| part.strip_prefix('+'))?;
+    let (start, count) = match new.split_once(',') {
+        Some((start, count)) => (start.parse::<usize>().ok()?, count.parse::<usize>().ok()?),
+        None => (new.parse::<usize>().ok()?, 1),
+    };
+
+    if count == 0 {
+        // Pure deletion after line `start`: touches its neighbours
+        Some([start.max(1), start + 1])
+    } else {
+        Some([start, start + count - 1])
+    }
+}
+
+/// Strip git's C-style quoting from a path
+fn unquote(path: &str) -> String {
+    let Some(inner) = path.strip_prefix('"').and_then(|p| p.strip_suffix('"')) else {
+        return path.to_string();
+    };
+
+    let mut bytes = Vec::with_capacity(inner.len());
+    let mut chars = inner.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            let mut buf = [0; 4];
+            bytes.extend_from_slice(c.encode_utf8(&mut buf).as_bytes());
+            continue;
+        }
+        match chars.next() {
+            Some('n') => bytes.push(b'\n'),
+            Some('t') => bytes.push(b'\t'),
+            Some(d @ '0'..='7') => {
+                let octal: String = std::iter::once(d).chain(chars.by_ref().take(2)).collect();
+                bytes.push(u8::from_str_radix(&octal, 8).unwrap_or(b'?'));
+            }
+            Some(other) => {
+                let mut buf = [0; 4];
+                bytes.extend_from_slice(other.encode_utf8(&mut buf).as_bytes());
+            }
+            None => {}
+        }
+    }
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_modified_files_and_hunks() {
+        let output = "\
+diff --git a/src/main.rs b/src/main.rs
+index 1111111..2222222 100644
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -3 +3 @@ fn main() {
+-    old();
++    new();
+@@ -10,0 +11,3 @@ fn main() {
++    one();
++    two();
++    three();
+@@ -20,2 +22,0 @@ fn helper() {
+-    gone();
+-    gone();
+";
+        let changes = parse_diff(output);
+        assert_eq!(changes.len(), 1);
+        let change = &changes[0];
+        assert_eq!(change.path, "src/main.rs");
+        assert_eq!(change.status, ChangeStatus::Modified);
+        assert_eq!(change.old_path, None);
+        // A pure deletion touches the lines on either side of it
+        assert_eq!(change.hunks, [[3, 3], [11, 13], [22, 23]]);
+    }
+
+    #[test]
+    fn parses_added_and_deleted_files() {
+        let output = "\
+diff --git a/src/new.rs b/src/new.rs
+new file mode 100644
+index 0000000..1111111
+--- /dev/null
++++ b/src/new.rs
+@@ -0,0 +1,2 @@
++fn a() {}
++fn b() {}
+diff --git a/src/old.rs b/src/old.rs
+deleted file mode 100644
+index 1111111..0000000
+--- a/src/old.rs
++++ /dev/null
+@@ -1,2 +0,0 @@
+-fn a() {}
+-fn b() {}
+";
+        let changes = parse_diff(output);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "src/new.rs");
+        assert_eq!(changes[0].status, ChangeStatus::Added);
+        assert_eq!(changes[0].hunks, [[1, 2]]);
+        assert_eq!(changes[1].path, "src/old.rs");
+        assert_eq!(changes[1].status, ChangeStatus::Deleted);
+        assert!(changes[1].touches([100, 200]));
+    }
+
+    #[test]
+    fn parses_renames() {
+        let output = "\
+diff --git a/src/auth.rs b/src/auth/mod.rs
+similarity index 90%
+rename from src/auth.rs
+rename to src/auth/mod.rs
+index 1111111..2222222 100644
+--- a/src/auth.rs
++++ b/src/auth/mod.rs
+@@ -4 +4 @@
+-old
++new
+diff --git a/a.rs b/b.rs
+similarity index 100%
+rename from a.rs
+rename to b.rs
+";
+        let changes = parse_diff(output);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "src/auth/mod.rs");
+        assert_eq!(changes[0].status, ChangeStatus::Renamed);
+        assert_eq!(changes[0].old_path.as_deref(), Some("src/auth.rs"));
+        assert_eq!(changes[0].hunks, [[4, 4]]);
+        assert!(changes[0].touches([1, 4]));
+        assert!(!changes[0].touches([5, 9]));
+        assert_eq!(changes[1].path, "b.rs");
+        assert_eq!(changes[1].old_path.as_deref(), Some("a.rs"));
+        assert!(changes[1].hunks.is_empty());
+    }
+
+    #[test]
+    fn parses_quoted_paths() {
+        let output = "\
+diff --git \"a/docs/caf\\303\\251 notes.md\" \"b/docs/caf\\303\\251 notes.md\"
+index 1111111..2222222 100644
+--- \"a/docs/caf\\303\\251 notes.md\"
++++ \"b/docs/caf\\303\\251 notes.md\"
+@@ -1 +1 @@
+-old
++new
+diff --git \"a/tab\\there.rs\" \"b/quote\\\"d.rs\"
+similarity index 100%
+rename from \"tab\\there.rs\"
+rename to \"quote\\\"d.rs\"
+";
+        let changes = parse_diff(output);
+        assert_eq!(changes[0].path, "docs/café notes.md");
+        assert_eq!(changes[1].path, "quote\"d.rs");
+        assert_eq!(changes[1].old_path.as_deref(), Some("tab\there.rs"));
+    }
+
+    #[test]
+    fn reads_file_lines_only_before_the_first_hunk() {
+        // Removing `-- a/old.sql` and adding `++ b/new.sql` in a deleted file
+        let output = "\
+diff --git a/db/schema.sql b/db/schema.sql
+deleted file mode 100644
+index 1111111..0000000
+--- a/db/schema.sql
++++ /dev/null
+@@ -1,2 +0,0 @@
+--- a/old.sql
+-create table t ();
+diff --git a/db/seed.sql b/db/seed.sql
+index 1111111..2222222 100644
+--- a/db/seed.sql
++++ b/db/seed.sql
+@@ -4,0 +5 @@
++++ b/new.sql
+";
+        let changes = parse_diff(output);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].path, "db/schema.sql");
+        assert_eq!(changes[0].status, ChangeStatus::Deleted);
+        assert_eq!(changes[1].path, "db/seed.sql");
+        assert_eq!(changes[1].hunks, [[5, 5]]);
+    }
+
+    #[test]
+    fn takes_paths_from_the_header_without_file_lines() {
+        let output = "\
+diff --git a/assets/logo.png b/assets/logo.png
+index 1111111..2222222 100644
+Binary files a/assets/logo.png and b/assets/logo.png differ
+diff --git a/my b/file.sh b/my b/file.sh
+old mode 100644
+new mode 100755
+diff --git \"a/tab\\there.bin\" \"b/tab\\there.bin\"
+index 1111111..2222222 100644
+Binary files \"a/tab\\there.bin\" and \"b/tab\\there.bin\" differ
+";
+        let paths: Vec<String> = parse_diff(output).into_iter().map(|c| c.path).collect();
+        assert_eq!(paths, ["assets/logo.png", "my b/file.sh", "tab\there.bin"]);
+    }
+
+    #[test]
+    fn ignores_output_before_the_first_file() {
+        assert!(parse_diff("").is_empty());
+        assert!(parse_diff("warning: something\n@@ -1 +1 @@\n").is_empty());
+    }
+
+    #[test]
+    fn unquote_handles_escapes() {
+        assert_eq!(unquote("src/main.rs"), "src/main.rs");
+        assert_eq!(unquote("\"src/main.rs\""), "src/main.rs");
+        assert_eq!(unquote("\"a\\tb\\nc\""), "a\tb\nc");
+        assert_eq!(unquote("\"say \\\"hi\\\"\""), "say \"hi\"");
+        assert_eq!(unquote("\"back\\\\slash\""), "back\\slash");
+        assert_eq!(unquote("\"caf\\303\\251\""), "café");
+        // Only fully quoted paths are unquoted
+        assert_eq!(unquote("\"half"), "\"half");
+        assert_eq!(unquote("\""), "\"");
+    }
+}
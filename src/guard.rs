@@ -0,0 +1,207 @@
+//! @acp:module "Guard"
+//! @acp:summary "Pre-commit check of staged changes against lock constraints"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Runs in-process without a daemon: loads the cache from disk, reads the
+//! staged diff and fails when frozen code is touched, or when restricted
+//! code is touched without an acknowledgement trailer in the commit message.
+
+use std::path::{Path, PathBuf};
+
+use acp::cache::Cache;
+use console::style;
+
+use crate::changes::{self, LockHit};
+use crate::git::{self, DiffTarget};
+use crate::locks;
+
+/// Commit message trailer acknowledging restricted changes
+pub const ACK_TRAILER: &str = "ACP-Acknowledged";
+
+/// Outcome of checking the staged diff
+pub struct GuardReport {
+    /// Staged files checked
+    pub files: usize,
+    /// Frozen and restricted files and symbols touched, frozen first
+    pub hits: Vec<LockHit>,
+    /// Reason given in the acknowledgement trailer
+    pub acknowledged: Option<String>,
+    /// Commit message that was searched for the trailer
+    pub message_file: Option<PathBuf>,
+}
+
+impl GuardReport {
+    fn frozen(&self) -> impl Iterator<Item = &LockHit> {
+        self.hits.iter().filter(|hit| hit.level == "frozen")
+    }
+
+    fn restricted(&self) -> impl Iterator<Item = &LockHit> {
+        self.hits.iter().filter(|hit| hit.level == "restricted")
+    }
+
+    /// Whether the commit may proceed
+    pub fn passed(&self) -> bool {
+        self.frozen().next().is_none()
+            && (self.restricted().next().is_none() || self.acknowledged.is_some())
+    }
+
+    /// Print a human-readable report to stderr
+    pub fn print(&self) {
+        if self.hits.is_empty() {
+            eprintln!(
+                "{} No locked code staged ({} files checked)",
+                style("✓").green(),
+                self.files
+            );
+            return;
+        }
+
+        if self.passed() {
+            eprintln!(
+                "{} Staged changes touch restricted code (acknowledged)",
+                style("!").yellow()
+            );
+        } else {
+            eprintln!("{} Staged changes touch locked code", style("✗").red());
+        }
+        eprintln!();
+
+        for hit in &self.hits {
+            let target = match (&hit.symbol, hit.lines) {
+                (Some(symbol), Some([start, end])) => {
+                    format!("{} (lines {}-{})", symbol, start, end)
+                }
+                (Some(symbol), None) => symbol.clone(),
+                (None, _) => hit.path.clone(),
+            };
+            let level = format!("{:<10}", hit.level);
+            let level = if hit.level == "frozen" {
+                style(level).red()
+            } else {
+                style(level).yellow()
+            };
+            eprintln!("  {}  {}", level, target);
+            if let Some(directive) = &hit.directive {
+                eprintln!("              {}", style(directive).dim());
+            }
+        }
+        eprintln!();
+
+        if self.frozen().next().is_some() {
+            eprintln!(
+                "Frozen code cannot be committed. Unstage it with: git restore --staged <path>"
+            );
+        }
+        if self.restricted().next().is_some() {
+            match &self.acknowledged {
+                Some(reason) => eprintln!("Restricted changes acknowledged: {}", reason),
+                None => {
+                    eprintln!("Restricted code needs an acknowledgement in the commit message:");
+                    eprintln!();
+                    eprintln!("    {}: <reason>", ACK_TRAILER);
+                    if self.message_file.is_none() {
+                        eprintln!();
+                        eprintln!(
+                            "Run as a commit-msg hook (acpd check-staged --message-file \"$1\") so the trailer can be read."
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Check the staged diff of `project_root` against its cache
+pub fn check_staged(
+    project_root: &Path,
+    message_file: Option<&Path>,
+) -> anyhow::Result<GuardReport> {
+    let cache_path = project_root.join(".acp").join("acp.cache.json");
+    let content = std::fs::read_to_string(&cache_path).map_err(|_| {
+        anyhow::anyhow!(
+            "No cache found at {}. Run 'acp index' first.",
+            cache_path.display()
+        )
+    })?;
+    let cache: Cache = serde_json::from_str(&content)?;
+
+    let staged = git::diff(project_root, &DiffTarget::Staged)?;
+    let files = changes::join(&cache, staged);
+
+    let hits = changes::lock_hits(&files)
+        .into_iter()
+        .filter(|hit| locks::LOCK_LEVELS.contains(&hit.level.as_str()))
+        .collect();
+
+    let acknowledged = match message_file {
+        Some(path) => ack_reason(&std::fs::read_to_string(path)?),
+        None => None,
+    };
+
+    Ok(GuardReport {
+        files: files.len(),
+        hits,
+        acknowledged,
+        message_file: message_file.map(Path::to_path_buf),
+    })
+}
+
+/// Reason given in an `ACP-Acknowledged: <reason>` trailer, ignoring comments
+fn ack_reason(message: &str) -> Option<String> {
+    message
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(':'))
+        .find(|(key, _)| key.trim().eq_ignore_ascii_case(ACK_TRAILER))
+        .map(|(_, reason)| reason.trim().to_string())
+        .filter(|reason| !reason.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use acp::constraints::ConstraintIndex;
+    use std::process::Command;
+
+    fn git(dir: &Path, args: &[&str]) {
+        let status = Command::new("git")
+            .args(args)
+            .current_dir(dir)
+            .status()
+            .unwrap();
+        assert!(status.success(), "git {:?} failed", args);
+    }
+
+    #[test]
+    fn staged_frozen_file_fails() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+
+        // `acp index` writes keys with a `./` prefix, git reports bare paths
+        let mut constraints = ConstraintIndex::default();
+        constraints
+            .by_lock_level
+            .insert("frozen".to_string(), vec!["./src/lib.rs".to_string()]);
+        let mut cache = Cache::new("test", ".");
+        cache.constraints = Some(constraints);
+        std::fs::create_dir_all(root.join(".acp")).unwrap();
+        std::fs::write(
+            root.join(".acp").join("acp.cache.json"),
+            serde_json::to_string(&cache).unwrap(),
+        )
+        .unwrap();
+
+        std::fs::create_dir_all(root.join("src")).unwrap();
+        std::fs::write(root.join("src").join("lib.rs"), "pub fn locked() {}\n").unwrap();
+        git(root, &["init", "-q"]);
+        git(root, &["add", "src/lib.rs"]);
+
+        let report = check_staged(root, None).unwrap();
+        assert_eq!(report.files, 1);
+        assert_eq!(report.hits.len(), 1);
+        assert_eq!(report.hits[0].level, "frozen");
+        assert!(!report.passed());
+    }
+}
@@ -0,0 +1,734 @@
+//! @acp:module "Incremental Indexer"
+//! @acp:summary "Re-parses changed source files and patches the loaded cache"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Re-indexes individual files the way `acp index` does (annotations,
+//! then AST symbols and call edges) and patches `Cache.files`,
+//! `Cache.symbols`, the call graph and the constraint index in place,
+//! then persists the updated cache to disk.
+
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Component, Path, PathBuf};
+
+use acp::ast::{AstParser, ExtractedSymbol, SymbolKind, Visibility as AstVisibility};
+use acp::cache::{
+    AnnotationProvenance, Cache, CallGraph, DomainEntry, SymbolEntry, SymbolType, Visibility,
+};
+use acp::config::Config;
+use acp::constraints::{
+    ConstraintIndex, Constraints, HackMarker, HackType, LockLevel, MutationConstraint,
+};
+use acp::parse::{AnnotationWithProvenance, HackAnnotation, ParseResult, Parser, SourceOrigin};
+use chrono::{DateTime, NaiveDate, Utc};
+use glob::Pattern;
+use serde::Serialize;
+use tracing::{debug, info, warn};
+use walkdir::WalkDir;
+
+use crate::jobs::Job;
+use crate::paths;
+use crate::state::AppState;
+
+/// Directories that never contain indexable sources
+const IGNORED_DIRS: &[&str] = &[".acp", ".git"];
+
+/// Outcome of an incremental index run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct IndexSummary {
+    /// Files re-parsed and patched into the cache
+    pub updated: usize,
+    /// Files removed from the cache
+    pub removed: usize,
+    /// Files that failed to parse, with the error message
+    pub errors: Vec<String>,
+}
+
+/// Include/exclude matcher built from the project config
+pub struct SourceMatcher {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+}
+
+impl SourceMatcher {
+    /// Build a matcher from the `include`/`exclude` globs in the config
+    pub fn from_config(config: &Config) -> Self {
+        fn compile(patterns: &[String]) -> Vec<Pattern> {
+            patterns
+                .iter()
+                .filter_map(|p| match Pattern::new(p) {
+                    Ok(pattern) => Some(pattern),
+                    Err(e) => {
+                        warn!("Ignoring invalid glob '{}': {}", p, e);
+                        None
+                    }
+                })
+                .collect()
+        }
+
+        Self {
+            include: compile(&config.include),
+            exclude: compile(&config.exclude),
+        }
+    }
+
+    /// Check whether a project-relative path should be indexed
+    pub fn matches(&self, rel_path: &str) -> bool {
+        if rel_path
+            .split('/')
+            .any(|segment| IGNORED_DIRS.contains(&segment))
+        {
+            return false;
+        }
+
+        let included = self.include.is_empty() || self.include.iter().any(|p| p.matches(rel_path));
+        included && !self.exclude.iter().any(|p| p.matches(rel_path))
+    }
+}
+
+/// Result of re-parsing a single file
+enum Parsed {
+    Updated {
+        path: String,
+        result: Box<ParseResult>,
+        modified: Option<DateTime<Utc>>,
+    },
+    Removed(String),
+    Failed {
+        path: String,
+        error: String,
+    },
+}
+
+/// Re-index the given paths and patch them into the loaded cache
+///
+/// Paths may be absolute or relative to the project root. Paths that do
+/// not match the configured include/exclude globs are ignored; paths that
+/// no longer exist are removed from the cache.
+pub async fn apply_changes(state: &AppState, paths: Vec<PathBuf>) -> anyhow::Result<IndexSummary> {
+    let root = canonical_root(state);
+
+    let changed: BTreeSet<String> = {
+        let config = state.config().await;
+        let matcher = SourceMatcher::from_config(&config);
+        paths
+            .iter()
+            .filter_map(|p| relative_path(&root, p))
+            .filter(|rel| matcher.matches(rel))
+            .collect()
+    };
+
+    if changed.is_empty() {
+        return Ok(IndexSummary::default());
+    }
+
+    debug!("Re-indexing {} changed file(s)", changed.len());
+    index_files(state, root, changed, None).await
+}
+
+/// Re-index the whole project, or only files under `scope`, reporting to `job`
+///
+/// Cached files under the scope that no longer exist on disk are removed.
+pub async fn reindex(
+    state: &AppState,
+    scope: Option<&str>,
+    job: &Job,
+) -> anyhow::Result<IndexSummary> {
+    let root = canonical_root(state);
+    let scope = scope
+        .map(|s| s.trim_start_matches("./").trim_matches('/').to_string())
+        .filter(|s| !s.is_empty());
+    let in_scope = |rel: &str| match &scope {
+        Some(prefix) => rel == prefix || rel.starts_with(&format!("{}/", prefix)),
+        None => true,
+    };
+
+    let mut paths = {
+        let config = state.config().await;
+        let matcher = SourceMatcher::from_config(&config);
+        let walk_root = root.clone();
+        tokio::task::spawn_blocking(move || discover_sources(&walk_root, &matcher)).await?
+    };
+    paths.retain(|rel| in_scope(rel));
+
+    // Include cached files that disappeared so they get removed
+    {
+        let cache = state.cache_async().await;
+        paths.extend(
+            cache
+                .files
+                .keys()
+                .map(|key| paths::strip_dot(key))
+                .filter(|rel| in_scope(rel))
+                .map(String::from),
+        );
+    }
+
+    job.set_total(paths.len());
+    index_files(state, root, paths, Some(job.clone())).await
+}
+
+/// Walk the project tree and collect every file the matcher accepts
+pub fn discover_sources(root: &Path, matcher: &SourceMatcher) -> BTreeSet<String> {
+    WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !IGNORED_DIRS.contains(&entry.file_name().to_string_lossy().as_ref())
+        })
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| relative_path(root, entry.path()))
+        .filter(|rel| matcher.matches(rel))
+        .collect()
+}
+
+async fn index_files(
+    state: &AppState,
+    root: PathBuf,
+    paths: BTreeSet<String>,
+    job: Option<Job>,
+) -> anyhow::Result<IndexSummary> {
+    let review_threshold = state.config().await.annotate.provenance.review_threshold;
+    let parsed = tokio::task::spawn_blocking(move || {
+        let parser = FileParser::new(review_threshold);
+        parse_files(&parser, &root, paths, job.as_ref())
+    })
+    .await?;
+
+    let mut summary = IndexSummary::default();
+    {
+        let mut cache = state.cache_mut().await;
+        for item in parsed {
+            match item {
+                Parsed::Updated {
+                    path,
+                    result,
+                    modified,
+                } => {
+                    let key = cache_key(&cache, &path);
+                    remove_file(&mut cache, &key);
+                    insert_file(&mut cache, &key, *result, modified);
+                    summary.updated += 1;
+                }
+                Parsed::Removed(path) => {
+                    if paths::cache_entry(&cache.files, &path).is_some() {
+                        remove_file(&mut cache, &path);
+                        summary.removed += 1;
+                    }
+                }
+                Parsed::Failed { path, error } => {
+                    warn!("Failed to parse {}: {}", path, error);
+                    summary.errors.push(format!("{}: {}", path, error));
+                }
+            }
+        }
+
+        // Failed parses alone leave the cache as it was
+        if summary.updated == 0 && summary.removed == 0 {
+            return Ok(summary);
+        }
+        refresh_stats(&mut cache);
+        cache.generated_at = Utc::now();
+    }
+
+    persist_cache(state).await?;
+    info!(
+        "Index updated: {} updated, {} removed, {} failed",
+        summary.updated,
+        summary.removed,
+        summary.errors.len()
+    );
+
+    Ok(summary)
+}
+
+/// Write the in-memory cache back to `.acp/acp.cache.json`
+///
+/// Writers are serialized, so the watcher and `POST /index` jobs never
+/// share the temporary file.
+pub async fn persist_cache(state: &AppState) -> anyhow::Result<()> {
+    let mut cache_file = state.cache_file().await;
+    let cache_path = state.cache_path();
+    let content = {
+        let cache = state.cache_async().await;
+        serde_json::to_string_pretty(&*cache)?
+    };
+
+    // Write to a temporary file first so readers never see a partial cache
+    let tmp_path = cache_path.with_extension("json.tmp");
+    tokio::fs::write(&tmp_path, &content).await?;
+    tokio::fs::rename(&tmp_path, &cache_path).await?;
+    cache_file.written(&content);
+
+    debug!("Cache persisted to {}", cache_path.display());
+    Ok(())
+}
+
+/// Convert a path into the project-relative, `/`-separated form of a cache key, without `./`
+pub fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let rel = if path.is_absolute() {
+        path.strip_prefix(root).ok()?
+    } else {
+        path
+    };
+
+    let segments: Vec<String> = rel
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(s) => Some(s.to_string_lossy().into_owned()),
+            _ => None,
+        })
+        .collect();
+
+    if segments.is_empty() {
+        None
+    } else {
+        Some(segments.join("/"))
+    }
+}
+
+/// Project root with symlinks resolved, as used for cache keys
+pub fn canonical_root(state: &AppState) -> PathBuf {
+    std::fs::canonicalize(state.project_root())
+        .unwrap_or_else(|_| state.project_root().to_path_buf())
+}
+
+/// Annotation and AST parsers, combined the way `acp index` combines them
+struct FileParser {
+    parser: Parser,
+    /// `None` if tree-sitter failed to initialise; symbols then come from annotations
+    ast: Option<AstParser>,
+    /// Confidence below which generated annotations are flagged for review
+    review_threshold: f64,
+}
+
+impl FileParser {
+    fn new(review_threshold: f64) -> Self {
+        let ast = match AstParser::new() {
+            Ok(ast) => Some(ast),
+            Err(e) => {
+                warn!("AST parser unavailable, indexing annotations only: {}", e);
+                None
+            }
+        };
+        Self {
+            parser: Parser::new(),
+            ast,
+            review_threshold,
+        }
+    }
+
+    /// Parse annotations, then take symbols and call edges from the AST
+    ///
+    /// AST symbols replace annotation symbols but keep their summaries, as
+    /// in a full index.
+    fn parse(&self, abs: &Path, path: &str) -> Result<ParseResult, String> {
+        let mut result = self.parser.parse(abs).map_err(|e| e.to_string())?;
+        let Ok(source) = std::fs::read_to_string(abs) else {
+            return Ok(result);
+        };
+
+        result.file.annotations = provenance(
+            &self.parser.parse_annotations_with_provenance(&source),
+            self.review_threshold,
+        );
+
+        let Some(ast) = &self.ast else {
+            return Ok(result);
+        };
+        let Ok(extracted) = ast.parse_file(abs, &source) else {
+            return Ok(result);
+        };
+
+        if !extracted.is_empty() {
+            let summaries: HashMap<String, String> = result
+                .symbols
+                .drain(..)
+                .filter_map(|s| Some((s.name, s.summary?)))
+                .collect();
+            result.symbols = extracted
+                .iter()
+                .map(|sym| {
+                    let mut symbol = symbol_from_ast(sym, path);
+                    if symbol.summary.is_none() {
+                        symbol.summary = summaries.get(&symbol.name).cloned();
+                    }
+                    symbol
+                })
+                .collect();
+        }
+
+        if let Ok(calls) = ast.parse_calls(abs, &source) {
+            result.calls.extend(
+                calls
+                    .into_iter()
+                    .filter(|call| !call.caller.is_empty())
+                    .map(|call| (call.caller, vec![call.callee])),
+            );
+        }
+
+        Ok(result)
+    }
+}
+
+fn parse_files(
+    parser: &FileParser,
+    root: &Path,
+    paths: BTreeSet<String>,
+    job: Option<&Job>,
+) -> Vec<Parsed> {
+    paths
+        .into_iter()
+        .map(|path| {
+            let parsed = parse_file(parser, root, path);
+            if let Some(job) = job {
+                job.file_scanned(match &parsed {
+                    Parsed::Failed { path, error } => Some(format!("{}: {}", path, error)),
+                    _ => None,
+                });
+            }
+            parsed
+        })
+        .collect()
+}
+
+fn parse_file(parser: &FileParser, root: &Path, path: String) -> Parsed {
+    let abs = root.join(&path);
+    let metadata = match std::fs::metadata(&abs) {
+        Ok(m) if m.is_file() => m,
+        _ => return Parsed::Removed(path),
+    };
+
+    match parser.parse(&abs, &path) {
+        Ok(result) => Parsed::Updated {
+            path,
+            result: Box::new(result),
+            modified: metadata.modified().ok().map(DateTime::<Utc>::from),
+        },
+        Err(error) => Parsed::Failed { path, error },
+    }
+}
+
+/// Cache symbol for an AST-extracted symbol, mapped as `acp index` maps it
+fn symbol_from_ast(sym: &ExtractedSymbol, path: &str) -> SymbolEntry {
+    let symbol_type = match sym.kind {
+        SymbolKind::Function => SymbolType::Function,
+        SymbolKind::Method => SymbolType::Method,
+        SymbolKind::Class | SymbolKind::Impl => SymbolType::Class,
+        SymbolKind::Struct => SymbolType::Struct,
+        SymbolKind::Interface => SymbolType::Interface,
+        SymbolKind::Trait => SymbolType::Trait,
+        SymbolKind::Enum | SymbolKind::EnumVariant => SymbolType::Enum,
+        SymbolKind::Constant | SymbolKind::Variable => SymbolType::Const,
+        SymbolKind::TypeAlias => SymbolType::Type,
+        SymbolKind::Module | SymbolKind::Namespace | SymbolKind::Property | SymbolKind::Field => {
+            SymbolType::Function
+        }
+    };
+    let visibility = match sym.visibility {
+        AstVisibility::Public => Visibility::Public,
+        AstVisibility::Protected => Visibility::Protected,
+        AstVisibility::Private | AstVisibility::Internal | AstVisibility::Crate => {
+            Visibility::Private
+        }
+    };
+
+    SymbolEntry {
+        name: sym.name.clone(),
+        qualified_name: sym
+            .qualified_name
+            .clone()
+            .unwrap_or_else(|| format!("{}:{}", path, sym.name)),
+        symbol_type,
+        file: path.to_string(),
+        lines: [sym.start_line, sym.end_line],
+        exported: sym.visibility == AstVisibility::Public,
+        signature: sym.signature.clone(),
+        summary: sym.doc_comment.clone(),
+        purpose: None,
+        constraints: None,
+        async_fn: sym.is_async,
+        visibility,
+        calls: Vec::new(),
+        called_by: Vec::new(),
+        git: None,
+        annotations: HashMap::new(),
+        behavioral: None,
+        lifecycle: None,
+        documentation: None,
+        performance: None,
+        type_info: None,
+    }
+}
+
+/// Provenance of each file-level annotation, keyed by `@acp:<name>`
+fn provenance(
+    annotations: &[AnnotationWithProvenance],
+    review_threshold: f64,
+) -> HashMap<String, AnnotationProvenance> {
+    annotations
+        .iter()
+        // `@acp:source*` annotations only carry provenance for the others
+        .filter(|ann| !ann.annotation.name.starts_with("source"))
+        .map(|ann| {
+            let value = ann.annotation.value.clone().unwrap_or_default();
+            let prov = match &ann.provenance {
+                Some(marker) => AnnotationProvenance {
+                    value,
+                    source: marker.source,
+                    confidence: marker.confidence,
+                    needs_review: marker.confidence.is_some_and(|c| c < review_threshold),
+                    reviewed: marker.reviewed.unwrap_or(false),
+                    reviewed_at: None,
+                    generated_at: Some(Utc::now().to_rfc3339()),
+                    generation_id: marker.generation_id.clone(),
+                },
+                // Annotations without markers were written by hand
+                None => AnnotationProvenance {
+                    value,
+                    source: SourceOrigin::Explicit,
+                    confidence: None,
+                    needs_review: false,
+                    reviewed: true,
+                    reviewed_at: None,
+                    generated_at: None,
+                    generation_id: None,
+                },
+            };
+            (format!("@acp:{}", ann.annotation.name), prov)
+        })
+        .collect()
+}
+
+/// Lock level named by an `@acp:lock` value
+fn parse_lock_level(value: &str) -> LockLevel {
+    match value.to_lowercase().as_str() {
+        "frozen" => LockLevel::Frozen,
+        "restricted" => LockLevel::Restricted,
+        "approval-required" => LockLevel::ApprovalRequired,
+        "tests-required" => LockLevel::TestsRequired,
+        "docs-required" => LockLevel::DocsRequired,
+        "experimental" => LockLevel::Experimental,
+        _ => LockLevel::Normal,
+    }
+}
+
+/// Key of a lock level in `ConstraintIndex.by_lock_level`, e.g. `approvalrequired`
+fn lock_level_key(level: LockLevel) -> String {
+    format!("{:?}", level).to_lowercase()
+}
+
+/// The key to store `path` under: its existing key, else in the `./` style of the cache
+fn cache_key(cache: &Cache, path: &str) -> String {
+    if let Some((key, _)) = paths::cache_entry(&cache.files, path) {
+        return key.clone();
+    }
+    let path = paths::strip_dot(path);
+    match cache.files.keys().next() {
+        Some(key) if key.starts_with("./") => format!("./{}", path),
+        _ => path.to_string(),
+    }
+}
+
+/// Remove `path` from `map` whether it is keyed with or without `./`
+fn remove_key<V>(map: &mut HashMap<String, V>, path: &str) {
+    let path = paths::strip_dot(path);
+    map.remove(path);
+    map.remove(&format!("./{}", path));
+}
+
+/// Remove a file and everything derived from it from the cache
+///
+/// `path` may be given with or without the `./` prefix of the cache keys.
+fn remove_file(cache: &mut Cache, path: &str) {
+    remove_key(&mut cache.files, path);
+    remove_key(&mut cache.source_files, path);
+
+    let removed: Vec<String> = cache
+        .symbols
+        .iter()
+        .filter(|(_, s)| paths::same_file(&s.file, path))
+        .map(|(name, _)| name.clone())
+        .collect();
+    for name in &removed {
+        cache.symbols.remove(name);
+    }
+
+    // Drop outgoing edges of the removed symbols. Incoming edges stay: the
+    // callers live in other files and still reference these names.
+    if let Some(graph) = cache.graph.as_mut() {
+        for name in &removed {
+            if let Some(callees) = graph.forward.remove(name) {
+                for callee in callees {
+                    if let Some(callers) = graph.reverse.get_mut(&callee) {
+                        callers.retain(|c| c != name);
+                        if callers.is_empty() {
+                            graph.reverse.remove(&callee);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(constraints) = cache.constraints.as_mut() {
+        remove_key(&mut constraints.by_file, path);
+        for files in constraints.by_lock_level.values_mut() {
+            files.retain(|f| !paths::same_file(f, path));
+        }
+        constraints
+            .hacks
+            .retain(|hack| !paths::same_file(&hack.file, path));
+    }
+
+    for domain in cache.domains.values_mut() {
+        domain.files.retain(|f| !paths::same_file(f, path));
+        domain.symbols.retain(|s| !removed.contains(s));
+    }
+    cache
+        .domains
+        .retain(|_, d| !d.files.is_empty() || !d.symbols.is_empty());
+}
+
+/// Insert a freshly parsed file into the cache
+///
+/// The file's `by_file` constraint record, lock level membership and hack
+/// markers are rebuilt as a full `acp index` would build them.
+fn insert_file(
+    cache: &mut Cache,
+    path: &str,
+    result: ParseResult,
+    modified: Option<DateTime<Utc>>,
+) {
+    let ParseResult {
+        mut file,
+        symbols,
+        calls,
+        lock_level,
+        lock_directive,
+        ai_hints,
+        hacks,
+        ..
+    } = result;
+
+    file.path = path.to_string();
+
+    let symbol_names: Vec<String> = symbols.iter().map(|s| s.name.clone()).collect();
+    for mut symbol in symbols {
+        symbol.file = path.to_string();
+        cache.symbols.insert(symbol.name.clone(), symbol);
+    }
+
+    if !calls.is_empty() {
+        let graph = cache.graph.get_or_insert_with(CallGraph::default);
+        for (caller, callees) in calls {
+            // The AST reports one edge per call site, so a caller appears repeatedly
+            for callee in callees {
+                let forward = graph.forward.entry(caller.clone()).or_default();
+                if !forward.contains(&callee) {
+                    forward.push(callee.clone());
+                }
+                let callers = graph.reverse.entry(callee).or_default();
+                if !callers.contains(&caller) {
+                    callers.push(caller.clone());
+                }
+            }
+        }
+    }
+
+    if lock_level.is_some() || !ai_hints.is_empty() {
+        let level = lock_level
+            .as_deref()
+            .map(parse_lock_level)
+            .unwrap_or_default();
+        let constraints = cache
+            .constraints
+            .get_or_insert_with(ConstraintIndex::default);
+        constraints.by_file.insert(
+            path.to_string(),
+            Constraints {
+                mutation: Some(MutationConstraint {
+                    level,
+                    reason: None,
+                    contact: None,
+                    requires_approval: level == LockLevel::ApprovalRequired,
+                    requires_tests: level == LockLevel::TestsRequired,
+                    requires_docs: level == LockLevel::DocsRequired,
+                    max_lines_changed: None,
+                    allowed_operations: None,
+                    forbidden_operations: None,
+                }),
+                auto_generated: lock_directive.is_none(),
+                directive: lock_directive,
+                ..Default::default()
+            },
+        );
+        let files = constraints
+            .by_lock_level
+            .entry(lock_level_key(level))
+            .or_default();
+        if !files.iter().any(|f| f == path) {
+            files.push(path.to_string());
+        }
+    }
+
+    if !hacks.is_empty() {
+        let constraints = cache
+            .constraints
+            .get_or_insert_with(ConstraintIndex::default);
+        constraints
+            .hacks
+            .extend(hacks.iter().map(|hack| hack_marker(path, hack)));
+    }
+
+    for domain_name in &file.domains {
+        let domain = cache
+            .domains
+            .entry(domain_name.clone())
+            .or_insert_with(|| DomainEntry {
+                name: domain_name.clone(),
+                files: Vec::new(),
+                symbols: Vec::new(),
+                description: None,
+            });
+        domain.files.push(path.to_string());
+        domain.symbols.extend(symbol_names.iter().cloned());
+    }
+
+    if let Some(modified) = modified {
+        cache.source_files.insert(path.to_string(), modified);
+    }
+    cache.files.insert(path.to_string(), file);
+}
+
+/// A hack marker for an `@acp:hack` annotation, as `acp index` records it
+fn hack_marker(path: &str, hack: &HackAnnotation) -> HackMarker {
+    HackMarker {
+        id: format!("{}:{}", path, hack.line),
+        hack_type: HackType::Workaround,
+        file: path.to_string(),
+        line: Some(hack.line),
+        created_at: Utc::now(),
+        author: None,
+        reason: hack
+            .reason
+            .clone()
+            .unwrap_or_else(|| "Temporary hack".to_string()),
+        ticket: hack.ticket.clone(),
+        expires: hack
+            .expires
+            .as_deref()
+            .and_then(|date| NaiveDate::parse_from_str(date, "%Y-%m-%d").ok())
+            .and_then(|date| date.and_hms_opt(0, 0, 0))
+            .map(|midnight| midnight.and_utc()),
+        original_code: None,
+        revert_instructions: None,
+    }
+}
+
+fn refresh_stats(cache: &mut Cache) {
+    cache.stats.files = cache.files.len();
+    cache.stats.symbols = cache.symbols.len();
+    cache.stats.lines = cache.files.values().map(|f| f.lines).sum();
+}
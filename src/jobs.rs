@@ -0,0 +1,173 @@
+//! @acp:module "Background Jobs"
+//! @acp:summary "Registry and progress tracking for long-running daemon jobs"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Jobs are started by API handlers and run as tokio tasks. Each job
+//! exposes a snapshot of its progress that `/jobs/{id}` can serve.
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::indexer::IndexSummary;
+
+/// Number of finished jobs kept around for status queries
+const MAX_FINISHED_JOBS: usize = 32;
+
+/// Lifecycle state of a job
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+/// Point-in-time view of a job, as served by the API
+#[derive(Debug, Clone, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    pub kind: &'static str,
+    pub scope: Option<String>,
+    pub status: JobStatus,
+    pub files_total: usize,
+    pub files_scanned: usize,
+    pub errors: Vec<String>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<IndexSummary>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Shared handle used by a running job to report progress
+#[derive(Clone)]
+pub struct Job {
+    info: Arc<Mutex<JobInfo>>,
+}
+
+impl Job {
+    /// Job identifier
+    pub fn id(&self) -> String {
+        self.lock().id.clone()
+    }
+
+    /// Copy of the current job state
+    pub fn snapshot(&self) -> JobInfo {
+        self.lock().clone()
+    }
+
+    /// Whether the job is still running
+    pub fn is_running(&self) -> bool {
+        self.lock().status == JobStatus::Running
+    }
+
+    /// Record the number of files the job will process
+    pub fn set_total(&self, total: usize) {
+        self.lock().files_total = total;
+    }
+
+    /// Record one processed file, with its error if it failed
+    pub fn file_scanned(&self, error: Option<String>) {
+        let mut info = self.lock();
+        info.files_scanned += 1;
+        if let Some(error) = error {
+            info.errors.push(error);
+        }
+    }
+
+    /// Mark the job as successfully finished
+    pub fn complete(&self, summary: IndexSummary) {
+        let mut info = self.lock();
+        info.status = JobStatus::Completed;
+        info.finished_at = Some(Utc::now());
+        info.result = Some(summary);
+    }
+
+    /// Mark the job as failed
+    pub fn fail(&self, error: impl ToString) {
+        let mut info = self.lock();
+        info.status = JobStatus::Failed;
+        info.finished_at = Some(Utc::now());
+        info.error = Some(error.to_string());
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, JobInfo> {
+        self.info.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Registry of running and recently finished jobs
+#[derive(Default)]
+pub struct JobRegistry {
+    next_id: AtomicU64,
+    jobs: Mutex<BTreeMap<u64, Job>>,
+}
+
+impl JobRegistry {
+    /// Register a new running job of `kind`
+    ///
+    /// Only one job of each kind runs at a time; if one is already running
+    /// it is returned as the error.
+    pub fn start(&self, kind: &'static str, scope: Option<String>) -> Result<Job, Job> {
+        let mut jobs = self.lock();
+        if let Some(running) = jobs
+            .values()
+            .find(|j| j.is_running() && j.snapshot().kind == kind)
+        {
+            return Err(running.clone());
+        }
+
+        let n = self.next_id.fetch_add(1, Ordering::Relaxed) + 1;
+        let job = Job {
+            info: Arc::new(Mutex::new(JobInfo {
+                id: format!("{}-{}", kind, n),
+                kind,
+                scope,
+                status: JobStatus::Running,
+                files_total: 0,
+                files_scanned: 0,
+                errors: Vec::new(),
+                started_at: Utc::now(),
+                finished_at: None,
+                result: None,
+                error: None,
+            })),
+        };
+        jobs.insert(n, job.clone());
+
+        // Evict the oldest finished jobs beyond the retention limit
+        let finished: Vec<u64> = jobs
+            .iter()
+            .filter(|(_, j)| !j.is_running())
+            .map(|(id, _)| *id)
+            .collect();
+        for id in finished
+            .iter()
+            .take(finished.len().saturating_sub(MAX_FINISHED_JOBS))
+        {
+            jobs.remove(id);
+        }
+
+        Ok(job)
+    }
+
+    /// Look up a job by its identifier
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.lock().values().find(|j| j.id() == id).cloned()
+    }
+
+    /// Whether any job is still running
+    pub fn has_running(&self) -> bool {
+        self.lock().values().any(|j| j.is_running())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, BTreeMap<u64, Job>> {
+        self.jobs.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
@@ -4,19 +4,135 @@
 //! @acp:layer service
 //!
 //! Manages daemon process lifecycle including daemonization,
-//! PID file management, and graceful shutdown.
+//! PID file management, the discovery file, and graceful shutdown.
 
 use std::fs;
-use std::path::Path;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::time::{Duration, Instant};
 
+use chrono::{DateTime, Utc};
 use console::style;
+use serde::{Deserialize, Serialize};
+
+use crate::settings::DaemonSettings;
 
 const PID_FILE: &str = ".acp/daemon.pid";
 const LOG_FILE: &str = ".acp/daemon.log";
+const DISCOVERY_FILE: &str = ".acp/daemon.json";
+
+/// How long `start` waits for the spawned daemon to report its address
+const STARTUP_WAIT: Duration = Duration::from_secs(5);
+
+/// Contents of `.acp/daemon.json`, used by clients to find a running daemon
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiscoveryInfo {
+    pub pid: u32,
+    pub version: String,
+    pub project_root: PathBuf,
+    /// Base URL for HTTP clients (absent when serving on a Unix socket)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    /// Address the daemon is listening on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub address: Option<SocketAddr>,
+    /// Unix socket path, when serving on a socket
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub socket: Option<PathBuf>,
+    /// File holding the admin bearer token (absent when auth is disabled)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_file: Option<PathBuf>,
+    pub started_at: DateTime<Utc>,
+}
+
+impl DiscoveryInfo {
+    /// Discovery info for a daemon listening on a TCP address
+    pub fn tcp(project_root: &Path, address: SocketAddr, tls: bool) -> Self {
+        // Clients cannot connect to an unspecified address; point them at loopback
+        let host = if address.ip().is_unspecified() {
+            match address {
+                SocketAddr::V4(_) => "127.0.0.1".to_string(),
+                SocketAddr::V6(_) => "[::1]".to_string(),
+            }
+        } else {
+            match address {
+                SocketAddr::V4(v4) => v4.ip().to_string(),
+                SocketAddr::V6(v6) => format!("[{}]", v6.ip()),
+            }
+        };
+
+        Self {
+            url: Some(format!(
+                "{}://{}:{}",
+                if tls { "https" } else { "http" },
+                host,
+                address.port()
+            )),
+            address: Some(address),
+            socket: None,
+            ..Self::new(project_root)
+        }
+    }
+
+    /// Discovery info for a daemon listening on a Unix socket
+    pub fn unix(project_root: &Path, socket: &Path) -> Self {
+        Self {
+            socket: Some(socket.to_path_buf()),
+            ..Self::new(project_root)
+        }
+    }
+
+    fn new(project_root: &Path) -> Self {
+        Self {
+            pid: std::process::id(),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            project_root: fs::canonicalize(project_root)
+                .unwrap_or_else(|_| project_root.to_path_buf()),
+            url: None,
+            address: None,
+            socket: None,
+            token_file: None,
+            started_at: Utc::now(),
+        }
+    }
+}
+
+/// Write the discovery file for a running daemon
+pub fn write_discovery(project_root: &Path, info: &DiscoveryInfo) -> anyhow::Result<()> {
+    let path = project_root.join(DISCOVERY_FILE);
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    fs::write(&path, serde_json::to_string_pretty(info)?)?;
+    Ok(())
+}
+
+/// Read the discovery file, if present and valid
+pub fn read_discovery(project_root: &Path) -> Option<DiscoveryInfo> {
+    fs::read_to_string(project_root.join(DISCOVERY_FILE))
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Remove the discovery file if it was written by this process
+pub fn remove_discovery(project_root: &Path) {
+    if let Some(info) = read_discovery(project_root) {
+        if info.pid == std::process::id() {
+            let _ = fs::remove_file(project_root.join(DISCOVERY_FILE));
+        }
+    }
+}
 
 /// Start the daemon in background mode
-pub fn start_daemon(project_root: impl AsRef<Path>, port: u16) -> anyhow::Result<()> {
+///
+/// `extra_args` are passed through to the spawned `acpd run` process;
+/// everything else it resolves from the environment and config itself.
+pub fn start_daemon(
+    project_root: impl AsRef<Path>,
+    settings: &DaemonSettings,
+    extra_args: &[String],
+) -> anyhow::Result<()> {
     let project_root = project_root.as_ref();
     let pid_path = project_root.join(PID_FILE);
 
@@ -44,12 +160,12 @@ pub fn start_daemon(project_root: impl AsRef<Path>, port: u16) -> anyhow::Result
     // Spawn daemon in background
     let log_path = project_root.join(LOG_FILE);
 
+    // Options are top-level flags, so they go before the subcommand
     let child = Command::new(&exe_path)
-        .arg("run")
-        .arg("--port")
-        .arg(port.to_string())
         .arg("-C")
         .arg(project_root)
+        .args(extra_args)
+        .arg("run")
         .stdout(fs::File::create(&log_path)?)
         .stderr(fs::File::create(&log_path)?)
         .spawn()?;
@@ -59,14 +175,28 @@ pub fn start_daemon(project_root: impl AsRef<Path>, port: u16) -> anyhow::Result
     // Write PID file
     fs::write(&pid_path, pid.to_string())?;
 
-    println!(
-        "{} Daemon started with PID {} (port {})",
-        style("✓").green(),
-        pid,
-        port
-    );
+    println!("{} Daemon started with PID {}", style("✓").green(), pid);
     println!("  Log file: {}", log_path.display());
-    println!("  API: http://127.0.0.1:{}/health", port);
+
+    // The daemon may pick its own port; wait for it to report the address
+    match wait_for_discovery(project_root, pid) {
+        Some(info) => print_endpoint(&info),
+        None => match &settings.socket {
+            Some(socket) => println!("  API: unix:{}", socket.display()),
+            None if settings.port == 0 => {
+                println!("  API: (pending, see {})", DISCOVERY_FILE)
+            }
+            None => println!(
+                "  API: {}://{}/health",
+                if settings.tls.is_some() {
+                    "https"
+                } else {
+                    "http"
+                },
+                settings.addr()
+            ),
+        },
+    }
 
     Ok(())
 }
@@ -113,8 +243,9 @@ pub fn stop_daemon(project_root: impl AsRef<Path>) -> anyhow::Result<()> {
                 );
             }
 
-            // Remove PID file
+            // Remove PID and discovery files
             let _ = fs::remove_file(&pid_path);
+            let _ = fs::remove_file(project_root.as_ref().join(DISCOVERY_FILE));
             Ok(())
         }
         None => {
@@ -135,8 +266,16 @@ pub fn check_status(project_root: impl AsRef<Path>) -> anyhow::Result<()> {
 
                 // Try to check health endpoint
                 // Note: This is a blocking call, could be improved with async
-                if let Ok(resp) = reqwest_sync_health(9222) {
-                    println!("  Health: {}", resp);
+                match read_discovery(project_root.as_ref()) {
+                    Some(info) => {
+                        print_endpoint(&info);
+                        if let Some(url) = &info.url {
+                            if let Ok(resp) = reqwest_sync_health(url) {
+                                println!("  Health: {}", resp);
+                            }
+                        }
+                    }
+                    None => println!("  No discovery file at {}", DISCOVERY_FILE),
                 }
             } else {
                 println!(
@@ -144,6 +283,7 @@ pub fn check_status(project_root: impl AsRef<Path>) -> anyhow::Result<()> {
                     style("!").yellow()
                 );
                 let _ = fs::remove_file(&pid_path);
+                let _ = fs::remove_file(project_root.as_ref().join(DISCOVERY_FILE));
             }
         }
         None => {
@@ -154,6 +294,28 @@ pub fn check_status(project_root: impl AsRef<Path>) -> anyhow::Result<()> {
     Ok(())
 }
 
+fn wait_for_discovery(project_root: &Path, pid: u32) -> Option<DiscoveryInfo> {
+    let deadline = Instant::now() + STARTUP_WAIT;
+    while Instant::now() < deadline {
+        if let Some(info) = read_discovery(project_root).filter(|info| info.pid == pid) {
+            return Some(info);
+        }
+        if !is_process_running(pid) {
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(100));
+    }
+    None
+}
+
+fn print_endpoint(info: &DiscoveryInfo) {
+    match (&info.url, &info.socket) {
+        (Some(url), _) => println!("  API: {}/health", url),
+        (None, Some(socket)) => println!("  API: unix:{}", socket.display()),
+        (None, None) => {}
+    }
+}
+
 fn read_pid(path: &Path) -> Option<u32> {
     fs::read_to_string(path)
         .ok()
@@ -177,13 +339,15 @@ fn is_process_running(pid: u32) -> bool {
     }
 }
 
-fn reqwest_sync_health(port: u16) -> Result<String, Box<dyn std::error::Error>> {
+fn reqwest_sync_health(base_url: &str) -> Result<String, Box<dyn std::error::Error>> {
     // Simple sync HTTP check - in production, use async
-    let url = format!("http://127.0.0.1:{}/health", port);
+    let url = format!("{}/health", base_url);
 
-    // Use std::process::Command to curl
+    // Use std::process::Command to curl; /health carries no credentials,
+    // so a self-signed certificate need not be verified
     let output = Command::new("curl")
         .arg("-s")
+        .arg("--insecure")
         .arg("-o")
         .arg("/dev/null")
         .arg("-w")
@@ -0,0 +1,21 @@
+//! @acp:module "Locks"
+//! @acp:summary "File lock levels from the constraint index"
+//! @acp:domain daemon
+//! @acp:layer service
+
+use acp::constraints::ConstraintIndex;
+
+use crate::paths;
+
+/// Lock levels reported by the daemon, most restrictive first
+pub const LOCK_LEVELS: [&str; 2] = ["frozen", "restricted"];
+
+/// The most restrictive lock level listing `path`, if any
+pub fn file_lock_level(index: &ConstraintIndex, path: &str) -> Option<&'static str> {
+    LOCK_LEVELS.into_iter().find(|level| {
+        index
+            .by_lock_level
+            .get(*level)
+            .is_some_and(|files| files.iter().any(|f| paths::same_file(f, path)))
+    })
+}
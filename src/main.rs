@@ -11,19 +11,41 @@
 //! - Variable expansion
 //! - Process lifecycle management
 
-use std::net::SocketAddr;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use clap::{Parser, Subcommand};
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+use settings::{AuthLayer, DaemonSettings, LogFormat, SettingsLayer, TlsLayer};
+
 mod api;
+mod auth;
+mod changes;
+mod context;
+mod git;
+mod guard;
+mod indexer;
+mod jobs;
 mod lifecycle;
+mod locks;
+mod map;
+mod origin;
+mod paths;
+mod primer;
+mod projects;
 mod server;
+mod settings;
+mod staleness;
 mod state;
+mod suggest;
+mod tls;
+mod watcher;
 
 /// ACP Daemon - Background service for codebase intelligence
+///
+/// Settings not given on the command line are read from `ACPD_*`
+/// environment variables, then the `daemon` section of `.acp.config.json`.
 #[derive(Parser, Debug)]
 #[command(name = "acpd")]
 #[command(author, version, about, long_about = None)]
@@ -35,17 +57,83 @@ struct Cli {
     #[arg(long, short = 'f')]
     foreground: bool,
 
-    /// HTTP server port
-    #[arg(long, default_value = "9222")]
-    port: u16,
+    /// HTTP server port, or `auto`/0 to pick a free one [default: 9222]
+    #[arg(long, value_parser = settings::parse_port)]
+    port: Option<u16>,
+
+    /// Address to bind to [default: 127.0.0.1]
+    #[arg(long)]
+    bind: Option<std::net::IpAddr>,
+
+    /// Serve on a Unix socket instead of TCP
+    #[arg(long, value_name = "PATH")]
+    socket: Option<PathBuf>,
 
     /// Project root directory
     #[arg(long, short = 'C')]
     directory: Option<PathBuf>,
 
-    /// Log level (trace, debug, info, warn, error)
-    #[arg(long, default_value = "info")]
-    log_level: String,
+    /// Log level (trace, debug, info, warn, error) [default: info]
+    #[arg(long)]
+    log_level: Option<String>,
+
+    /// Log format (text, json) [default: text]
+    #[arg(long)]
+    log_format: Option<LogFormat>,
+
+    /// Disable token authentication (every client gets full access)
+    #[arg(long)]
+    no_auth: bool,
+
+    /// Serve HTTPS using this PEM certificate chain
+    #[arg(long, value_name = "PATH", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// PEM private key for --tls-cert
+    #[arg(long, value_name = "PATH", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// Require client certificates signed by this PEM CA certificate
+    #[arg(long, value_name = "PATH")]
+    tls_client_ca: Option<PathBuf>,
+
+    /// Disable file watching and incremental re-indexing
+    #[arg(long)]
+    no_watch: bool,
+
+    /// Additional project root to serve under /projects/{id} (repeatable)
+    #[arg(long = "project", value_name = "PATH")]
+    projects: Vec<PathBuf>,
+
+    /// Unload additional projects after this many idle seconds [default: 600]
+    #[arg(long)]
+    idle_timeout: Option<u64>,
+}
+
+impl Cli {
+    /// Settings given explicitly on the command line
+    fn settings_layer(&self) -> SettingsLayer {
+        SettingsLayer {
+            bind: self.bind,
+            port: self.port,
+            socket: self.socket.clone(),
+            log_level: self.log_level.clone(),
+            log_format: self.log_format,
+            auth: AuthLayer {
+                enabled: self.no_auth.then_some(false),
+                keys_file: None,
+            },
+            tls: TlsLayer {
+                cert: self.tls_cert.clone(),
+                key: self.tls_key.clone(),
+                client_ca: self.tls_client_ca.clone(),
+            },
+            watch: self.no_watch.then_some(false),
+            projects: (!self.projects.is_empty()).then(|| self.projects.clone()),
+            idle_timeout_secs: self.idle_timeout,
+            ..Default::default()
+        }
+    }
 }
 
 #[derive(Subcommand, Debug)]
@@ -62,51 +150,99 @@ enum Commands {
     Status,
     /// Run daemon in foreground (alias for --foreground)
     Run,
+    /// Generate a self-signed TLS certificate in .acp/tls/
+    TlsCert {
+        /// Additional host name or IP address (repeatable)
+        #[arg(long = "host", value_name = "NAME")]
+        hosts: Vec<String>,
+        /// Replace an existing certificate
+        #[arg(long)]
+        force: bool,
+    },
+    /// Check staged changes against frozen and restricted code (for git hooks)
+    CheckStaged {
+        /// Commit message to search for an acknowledgement trailer (commit-msg hook's $1)
+        #[arg(long, value_name = "FILE")]
+        message_file: Option<PathBuf>,
+    },
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
-    // Initialize logging
-    init_logging(&cli.log_level);
-
     // Determine project root
     let project_root = cli
         .directory
+        .clone()
         .unwrap_or_else(|| std::env::current_dir().expect("Failed to get current directory"));
 
+    // Settings are only resolved for commands that serve, so `stop`,
+    // `status` and the git hooks keep working with a broken configuration
+    let cli_settings = cli.settings_layer();
+    let forward_args = cli_settings.to_args();
+
     match cli.command {
         Some(Commands::Start { foreground }) => {
+            let settings = resolve_settings(cli_settings, &project_root)?;
             if foreground || cli.foreground {
-                run_foreground(project_root, cli.port).await
+                run_foreground(project_root, settings).await
             } else {
-                lifecycle::start_daemon(project_root, cli.port)
+                lifecycle::start_daemon(project_root, &settings, &forward_args)
             }
         }
         Some(Commands::Stop) => lifecycle::stop_daemon(&project_root),
         Some(Commands::Status) => lifecycle::check_status(&project_root),
+        Some(Commands::TlsCert { hosts, force }) => {
+            let (cert, key) = tls::generate_self_signed(&project_root, &hosts, force)?;
+            println!("Certificate: {}", cert.display());
+            println!("Private key: {}", key.display());
+            println!(
+                "Serve it with: acpd --tls-cert {} --tls-key {} start",
+                tls::CERT_FILE,
+                tls::KEY_FILE
+            );
+            Ok(())
+        }
+        Some(Commands::CheckStaged { message_file }) => {
+            let report = guard::check_staged(&project_root, message_file.as_deref())?;
+            report.print();
+            if !report.passed() {
+                std::process::exit(1);
+            }
+            Ok(())
+        }
         Some(Commands::Run) | None => {
             // Default: run in foreground
-            run_foreground(project_root, cli.port).await
+            let settings = resolve_settings(cli_settings, &project_root)?;
+            run_foreground(project_root, settings).await
         }
     }
 }
 
-fn init_logging(level: &str) {
+/// Resolve settings (CLI > env > config > defaults) and initialize logging
+fn resolve_settings(cli: SettingsLayer, project_root: &Path) -> anyhow::Result<DaemonSettings> {
+    let settings = DaemonSettings::resolve(cli, project_root)?;
+    init_logging(&settings);
+    Ok(settings)
+}
+
+fn init_logging(settings: &DaemonSettings) {
     let filter = tracing_subscriber::EnvFilter::try_from_default_env()
-        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(level));
+        .unwrap_or_else(|_| tracing_subscriber::EnvFilter::new(&settings.log_level));
 
-    tracing_subscriber::registry()
-        .with(filter)
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    let registry = tracing_subscriber::registry().with(filter);
+    match settings.log_format {
+        LogFormat::Text => registry.with(tracing_subscriber::fmt::layer()).init(),
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().json())
+            .init(),
+    }
 }
 
-async fn run_foreground(project_root: PathBuf, port: u16) -> anyhow::Result<()> {
+async fn run_foreground(project_root: PathBuf, settings: DaemonSettings) -> anyhow::Result<()> {
     info!("Starting ACP daemon in foreground mode");
     info!("Project root: {}", project_root.display());
-    info!("HTTP port: {}", port);
 
     // Load ACP state (config, cache, vars)
     let state = match state::AppState::load(&project_root).await {
@@ -126,25 +262,142 @@ async fn run_foreground(project_root: PathBuf, port: u16) -> anyhow::Result<()>
         );
     }
 
+    // Watch sources and re-index incrementally; dropped on shutdown
+    let _watcher = if settings.watch {
+        match watcher::spawn(state.clone()) {
+            Ok(handle) => Some(handle),
+            Err(e) => {
+                warn!("File watching disabled: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    // Register additional projects; they load lazily on first request
+    let registry =
+        projects::ProjectRegistry::new(state.clone(), settings.idle_timeout, settings.watch);
+    for root in &settings.projects {
+        match registry.add(root, None).await {
+            Ok(project) => info!("Serving {} at /projects/{}", root.display(), project.id),
+            Err(e) => warn!("Skipping project {}: {}", root.display(), e),
+        }
+    }
+    let _eviction = registry.spawn_eviction();
+
+    // Generate the admin token and load API keys
+    let authenticator = auth::Authenticator::init(&project_root, &settings.auth)?;
+    let token_file = authenticator
+        .as_ref()
+        .map(|_| project_root.join(auth::TOKEN_FILE));
+
     // Build router
-    let app = server::create_router(state);
+    let app = server::create_router(state, registry, authenticator, &settings);
 
     // Bind and serve
-    let addr = SocketAddr::from(([127, 0, 0, 1], port));
-    info!("Listening on http://{}", addr);
+    match &settings.socket {
+        #[cfg(unix)]
+        Some(socket) => {
+            // Remove a stale socket left behind by an earlier run
+            let _ = std::fs::remove_file(socket);
+            info!("Listening on unix:{}", socket.display());
+            if settings.tls.is_some() {
+                warn!("TLS settings are ignored when serving on a Unix socket");
+            }
 
-    let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+            let listener = tokio::net::UnixListener::bind(socket)?;
+            publish_discovery(lifecycle::DiscoveryInfo {
+                token_file,
+                ..lifecycle::DiscoveryInfo::unix(&project_root, socket)
+            });
+            axum::serve(listener, app)
+                .with_graceful_shutdown(shutdown_signal())
+                .await?;
+            let _ = std::fs::remove_file(socket);
+        }
+        #[cfg(not(unix))]
+        Some(_) => anyhow::bail!("Unix sockets are not supported on this platform"),
+        None => {
+            let listener = tokio::net::TcpListener::bind(settings.addr()).await?;
+            let addr = listener.local_addr()?;
+            let scheme = if settings.tls.is_some() {
+                "https"
+            } else {
+                "http"
+            };
+            info!("Listening on {}://{}", scheme, addr);
 
+            publish_discovery(lifecycle::DiscoveryInfo {
+                token_file,
+                ..lifecycle::DiscoveryInfo::tcp(&project_root, addr, settings.tls.is_some())
+            });
+            match &settings.tls {
+                Some(tls_settings) => serve_tls(listener, tls_settings, app).await?,
+                None => {
+                    axum::serve(listener, app)
+                        .with_graceful_shutdown(shutdown_signal())
+                        .await?
+                }
+            }
+        }
+    }
+
+    lifecycle::remove_discovery(&project_root);
     info!("Daemon stopped");
     Ok(())
 }
 
+/// Serve HTTPS on an already bound listener until shutdown
+async fn serve_tls(
+    listener: tokio::net::TcpListener,
+    settings: &settings::TlsSettings,
+    app: axum::Router,
+) -> anyhow::Result<()> {
+    let config = tls::server_config(settings)?;
+
+    let handle = axum_server::Handle::new();
+    let shutdown = handle.clone();
+    tokio::spawn(async move {
+        shutdown_signal().await;
+        shutdown.graceful_shutdown(Some(std::time::Duration::from_secs(10)));
+    });
+
+    axum_server::from_tcp_rustls(listener.into_std()?, config)?
+        .handle(handle)
+        .serve(app.into_make_service())
+        .await?;
+    Ok(())
+}
+
+/// Write `.acp/daemon.json` so clients can find this daemon
+fn publish_discovery(info: lifecycle::DiscoveryInfo) {
+    if let Err(e) = lifecycle::write_discovery(&info.project_root, &info) {
+        warn!("Failed to write discovery file: {}", e);
+    }
+}
+
 async fn shutdown_signal() {
-    tokio::signal::ctrl_c()
-        .await
-        .expect("Failed to install CTRL+C handler");
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install CTRL+C handler");
+    };
+
+    // `acpd stop` sends SIGTERM
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {},
+        _ = terminate => {},
+    }
     info!("Received shutdown signal");
 }
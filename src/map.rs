@@ -0,0 +1,700 @@
+//! @acp:module "Project Map"
+//! @acp:summary "Annotated directory tree of indexed files"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Builds the tree from cache paths, rooted at any directory or file.
+//! Files carry their purpose, domains, layer and lock level; directories
+//! aggregate file, symbol, frozen-file and TODO counts over their whole
+//! subtree, including levels cut off by the depth limit. Maps render as
+//! JSON, an ASCII tree or a markdown list; text renderings are fitted to a
+//! token budget by showing fewer levels.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use acp::cache::{Cache, FileEntry};
+use serde::{Deserialize, Serialize};
+
+use crate::locks;
+use crate::paths;
+use crate::primer::tokens::TokenCounter;
+use crate::suggest;
+
+/// Errors building a map
+#[derive(Debug, thiserror::Error)]
+pub enum MapError {
+    #[error("path '{path}' is not an indexed file or directory")]
+    NotFound {
+        path: String,
+        suggestions: Vec<String>,
+    },
+}
+
+/// Inline annotation types counted as TODOs
+const TODO_TYPES: &[&str] = &["todo", "fixme"];
+
+#[derive(Debug, Serialize)]
+pub struct MapNode {
+    pub name: String,
+    /// Project-relative path (`.` for the project root)
+    pub path: String,
+    #[serde(rename = "type")]
+    pub node_type: &'static str,
+    /// File purpose, else summary
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub purpose: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub layer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lock_level: Option<&'static str>,
+    /// Exports of a file
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub symbols: Option<usize>,
+    /// Indexed symbols in a file (exported or not)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub indexed_symbols: Option<usize>,
+    /// Totals over a directory's subtree
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<DirStats>,
+    /// Inline annotations of a file, when requested
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub inline: Vec<InlineNote>,
+    /// Directory contents; absent past the depth limit
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub children: Option<Vec<MapNode>>,
+}
+
+#[derive(Debug, Clone, Copy, Default, Serialize)]
+pub struct DirStats {
+    pub files: usize,
+    pub symbols: usize,
+    pub frozen: usize,
+    pub todos: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InlineNote {
+    pub line: usize,
+    #[serde(rename = "type")]
+    pub annotation_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub value: Option<String>,
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub directive: String,
+}
+
+/// How a map is returned
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapFormat {
+    /// Nested `MapNode`s
+    #[default]
+    Json,
+    /// `├──` tree, one line per node
+    Tree,
+    /// Nested bullet list
+    Markdown,
+}
+
+impl MapFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            MapFormat::Json => "json",
+            MapFormat::Tree => "tree",
+            MapFormat::Markdown => "markdown",
+        }
+    }
+}
+
+/// A text rendering of a map
+pub struct RenderedMap {
+    pub content: String,
+    /// Levels shown below the root
+    pub depth: usize,
+    /// Lines were cut because even the root alone did not fit
+    pub truncated: bool,
+    pub tokens: u32,
+}
+
+/// What to include in a map
+pub struct MapOptions {
+    /// Normalised root path; empty or `.` for the project root
+    pub root: String,
+    /// Levels below the root to expand
+    pub depth: usize,
+    /// Include each file's inline annotations
+    pub inline: bool,
+}
+
+/// The map of a project subtree
+#[derive(Debug)]
+pub struct ProjectMap {
+    pub tree: MapNode,
+    pub total_files: usize,
+    pub total_dirs: usize,
+}
+
+/// Directory index of the cache: children per directory, `.` being the root
+///
+/// Paths are cache keys without their `./` prefix.
+struct Index<'a> {
+    cache: &'a Cache,
+    files: HashMap<&'a str, &'a FileEntry>,
+    dirs: BTreeMap<String, BTreeSet<String>>,
+    symbols: HashMap<&'a str, usize>,
+}
+
+impl ProjectMap {
+    pub fn build(cache: &Cache, options: &MapOptions) -> Result<Self, MapError> {
+        let index = Index::new(cache);
+        let root = match options.root.trim_end_matches('/') {
+            "" => ".",
+            root => root,
+        };
+
+        if !index.dirs.contains_key(root) && !index.files.contains_key(root) {
+            let candidates = index
+                .dirs
+                .keys()
+                .map(String::as_str)
+                .chain(index.files.keys().copied());
+            return Err(MapError::NotFound {
+                path: root.to_string(),
+                suggestions: suggest::closest(root, candidates),
+            });
+        }
+
+        let prefix = format!("{}/", root);
+        let within = |path: &str| root == "." || path == root || path.starts_with(&prefix);
+        let total_files = index.files.keys().filter(|path| within(path)).count();
+        let total_dirs = index.dirs.keys().filter(|path| within(path)).count();
+
+        Ok(Self {
+            tree: index.node(root, 0, options),
+            total_files,
+            total_dirs,
+        })
+    }
+}
+
+impl ProjectMap {
+    /// Render as text in at most `budget` tokens, dropping the deepest levels first
+    pub fn render_within(
+        &self,
+        format: MapFormat,
+        depth: usize,
+        budget: Option<u32>,
+        counter: TokenCounter,
+    ) -> RenderedMap {
+        let rendered = |depth: usize| {
+            let content = self.render(format, depth);
+            let tokens = counter.count(&content);
+            RenderedMap {
+                content,
+                depth,
+                truncated: false,
+                tokens,
+            }
+        };
+        let fits = |map: &RenderedMap| budget.is_none_or(|budget| map.tokens <= budget);
+
+        let full = rendered(depth.min(height(&self.tree)));
+        if fits(&full) {
+            return full;
+        }
+
+        // Output only grows with depth: search for the deepest level that fits
+        let (mut low, mut high) = (0, full.depth);
+        let mut best = None;
+        while low < high {
+            let candidate = rendered(low + (high - low) / 2);
+            if fits(&candidate) {
+                low = candidate.depth + 1;
+                best = Some(candidate);
+            } else {
+                high = candidate.depth;
+            }
+        }
+        best.unwrap_or_else(|| cut(&self.render(format, 0), budget.unwrap_or(0), counter))
+    }
+
+    /// Text rendering with `depth` levels below the root
+    pub fn render(&self, format: MapFormat, depth: usize) -> String {
+        let mut lines = Vec::new();
+        match format {
+            MapFormat::Markdown => markdown_lines(&self.tree, 0, depth, &mut lines),
+            _ => {
+                lines.push(label(&self.tree, true, false));
+                tree_lines(&self.tree, "", 0, depth, &mut lines);
+            }
+        }
+        lines.join("\n")
+    }
+}
+
+/// Levels below `node` in the built tree
+fn height(node: &MapNode) -> usize {
+    node.children
+        .iter()
+        .flatten()
+        .map(|child| height(child) + 1)
+        .max()
+        .unwrap_or(0)
+}
+
+/// Keep the leading lines that fit in `budget` with a note of how many were cut
+fn cut(content: &str, budget: u32, counter: TokenCounter) -> RenderedMap {
+    let lines: Vec<&str> = content.lines().collect();
+    let note = |kept: usize| format!("... (+{})", plural(lines.len() - kept, "line"));
+
+    let mut kept = 0;
+    let mut tokens = 0;
+    for line in &lines {
+        let cost = counter.count(&format!("{}\n", line));
+        if tokens + cost + counter.count(&note(kept + 1)) > budget {
+            break;
+        }
+        tokens += cost;
+        kept += 1;
+    }
+    let note = note(kept);
+    tokens += counter.count(&note);
+    let mut content: Vec<&str> = lines[..kept].to_vec();
+    content.push(&note);
+    RenderedMap {
+        content: content.join("\n"),
+        depth: 0,
+        truncated: true,
+        tokens,
+    }
+}
+
+fn tree_lines(node: &MapNode, prefix: &str, level: usize, depth: usize, lines: &mut Vec<String>) {
+    let inline = node.inline.iter().map(inline_label);
+    let children: Vec<Child> = if level < depth {
+        node.children
+            .iter()
+            .flatten()
+            .map(Child::Node)
+            .chain(inline.map(Child::Note))
+            .collect()
+    } else {
+        inline.map(Child::Note).collect()
+    };
+
+    for (i, child) in children.iter().enumerate() {
+        let last = i + 1 == children.len();
+        let (branch, indent) = if last {
+            ("└── ", "    ")
+        } else {
+            ("├── ", "│   ")
+        };
+        match child {
+            Child::Node(child) => {
+                lines.push(format!(
+                    "{}{}{}",
+                    prefix,
+                    branch,
+                    label(child, false, false)
+                ));
+                tree_lines(
+                    child,
+                    &format!("{}{}", prefix, indent),
+                    level + 1,
+                    depth,
+                    lines,
+                );
+            }
+            Child::Note(note) => lines.push(format!("{}{}{}", prefix, branch, note)),
+        }
+    }
+}
+
+fn markdown_lines(node: &MapNode, level: usize, depth: usize, lines: &mut Vec<String>) {
+    let indent = "  ".repeat(level);
+    lines.push(format!("{}- {}", indent, label(node, level == 0, true)));
+    for note in &node.inline {
+        lines.push(format!("{}  - {}", indent, inline_label(note)));
+    }
+    if level < depth {
+        for child in node.children.iter().flatten() {
+            markdown_lines(child, level + 1, depth, lines);
+        }
+    }
+}
+
+enum Child<'a> {
+    Node(&'a MapNode),
+    Note(String),
+}
+
+/// One-line description of a node: name, purpose, lock and totals
+fn label(node: &MapNode, root: bool, markdown: bool) -> String {
+    let name = if root { &node.path } else { &node.name };
+    match &node.stats {
+        Some(stats) => {
+            let name = format!("{}/", name.trim_end_matches('/'));
+            let name = if markdown {
+                format!("**{}**", name)
+            } else {
+                name
+            };
+            let mut totals = vec![plural(stats.files, "file"), plural(stats.symbols, "symbol")];
+            if stats.frozen > 0 {
+                totals.push(format!("{} frozen", stats.frozen));
+            }
+            if stats.todos > 0 {
+                totals.push(plural(stats.todos, "TODO"));
+            }
+            format!("{} ({})", name, totals.join(", "))
+        }
+        None => {
+            let mut text = if markdown {
+                format!("`{}`", name)
+            } else {
+                name.to_string()
+            };
+            if let Some(purpose) = &node.purpose {
+                text.push_str(&format!(" - {}", purpose));
+            }
+            if let Some(level) = node.lock_level {
+                text.push_str(&format!(" [{}]", level));
+            }
+            text
+        }
+    }
+}
+
+fn inline_label(note: &InlineNote) -> String {
+    let text = note.value.as_deref().unwrap_or(&note.directive);
+    format!("L{} {}: {}", note.line, note.annotation_type, text)
+}
+
+fn plural(count: usize, noun: &str) -> String {
+    if count == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", count, noun)
+    }
+}
+
+impl<'a> Index<'a> {
+    fn new(cache: &'a Cache) -> Self {
+        let files: HashMap<&str, &FileEntry> = cache
+            .files
+            .iter()
+            .map(|(key, file)| (paths::strip_dot(key), file))
+            .collect();
+
+        let mut dirs: BTreeMap<String, BTreeSet<String>> = BTreeMap::new();
+        for path in files.keys() {
+            let parts: Vec<&str> = path.split('/').collect();
+            for i in 0..parts.len() {
+                let dir = if i == 0 {
+                    ".".to_string()
+                } else {
+                    parts[..i].join("/")
+                };
+                let child = parts[..=i].join("/");
+                // A key such as `.` would make the root its own child
+                if child != dir {
+                    dirs.entry(dir).or_default().insert(child);
+                }
+            }
+        }
+
+        let mut symbols: HashMap<&str, usize> = HashMap::new();
+        for symbol in cache.symbols.values() {
+            *symbols.entry(paths::strip_dot(&symbol.file)).or_default() += 1;
+        }
+
+        Self {
+            cache,
+            files,
+            dirs,
+            symbols,
+        }
+    }
+
+    fn node(&self, path: &str, depth: usize, options: &MapOptions) -> MapNode {
+        let name = path.rsplit('/').next().unwrap_or(path).to_string();
+        match self.files.get(path) {
+            Some(file) => self.file_node(name, path, file, options),
+            None => {
+                let children = self.dirs.get(path).into_iter().flatten();
+                let children = (depth < options.depth).then(|| {
+                    children
+                        .map(|child| self.node(child, depth + 1, options))
+                        .collect()
+                });
+                MapNode {
+                    name,
+                    path: path.to_string(),
+                    node_type: "directory",
+                    purpose: None,
+                    domains: Vec::new(),
+                    layer: None,
+                    lock_level: None,
+                    symbols: None,
+                    indexed_symbols: None,
+                    stats: Some(self.stats(path)),
+                    inline: Vec::new(),
+                    children,
+                }
+            }
+        }
+    }
+
+    fn file_node(
+        &self,
+        name: String,
+        path: &str,
+        file: &FileEntry,
+        options: &MapOptions,
+    ) -> MapNode {
+        let inline = if options.inline {
+            file.inline
+                .iter()
+                .map(|annotation| InlineNote {
+                    line: annotation.line,
+                    annotation_type: annotation.annotation_type.clone(),
+                    value: annotation.value.clone(),
+                    directive: annotation.directive.clone(),
+                })
+                .collect()
+        } else {
+            Vec::new()
+        };
+        MapNode {
+            name,
+            path: path.to_string(),
+            node_type: "file",
+            purpose: file.purpose.clone().or_else(|| file.summary.clone()),
+            domains: file.domains.clone(),
+            layer: file.layer.clone(),
+            lock_level: self.lock_level(path),
+            symbols: Some(file.exports.len()),
+            indexed_symbols: Some(self.symbols.get(path).copied().unwrap_or(0)),
+            stats: None,
+            inline,
+            children: None,
+        }
+    }
+
+    /// Totals over every file below `dir`
+    fn stats(&self, dir: &str) -> DirStats {
+        let mut stats = DirStats::default();
+        for child in self.dirs.get(dir).into_iter().flatten() {
+            match self.files.get(child.as_str()) {
+                Some(file) => {
+                    stats.files += 1;
+                    stats.symbols += self.symbols.get(child.as_str()).copied().unwrap_or(0);
+                    stats.frozen += usize::from(self.lock_level(child) == Some("frozen"));
+                    stats.todos += file
+                        .inline
+                        .iter()
+                        .filter(|a| TODO_TYPES.contains(&a.annotation_type.to_lowercase().as_str()))
+                        .count();
+                }
+                None => {
+                    let sub = self.stats(child);
+                    stats.files += sub.files;
+                    stats.symbols += sub.symbols;
+                    stats.frozen += sub.frozen;
+                    stats.todos += sub.todos;
+                }
+            }
+        }
+        stats
+    }
+
+    fn lock_level(&self, path: &str) -> Option<&'static str> {
+        let index = self.cache.constraints.as_ref()?;
+        locks::file_lock_level(index, path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(keys: &[&str]) -> Cache {
+        let mut cache = Cache::new("test", ".");
+        for key in keys {
+            let file = serde_json::json!({ "path": key, "lines": 10, "language": "rust" });
+            cache
+                .files
+                .insert(key.to_string(), serde_json::from_value(file).unwrap());
+        }
+        cache
+    }
+
+    fn file(name: &str) -> MapNode {
+        MapNode {
+            name: name.to_string(),
+            path: name.to_string(),
+            node_type: "file",
+            purpose: None,
+            domains: Vec::new(),
+            layer: None,
+            lock_level: None,
+            symbols: None,
+            indexed_symbols: None,
+            stats: None,
+            inline: Vec::new(),
+            children: None,
+        }
+    }
+
+    fn dir(name: &str, children: Vec<MapNode>) -> MapNode {
+        MapNode {
+            node_type: "directory",
+            stats: Some(DirStats::default()),
+            children: Some(children),
+            ..file(name)
+        }
+    }
+
+    fn note(line: usize) -> InlineNote {
+        InlineNote {
+            line,
+            annotation_type: "todo".to_string(),
+            value: Some("handle the remaining error cases".to_string()),
+            directive: String::new(),
+        }
+    }
+
+    /// `.` > `src` > `auth` > `mod.rs`, three levels deep
+    fn project() -> ProjectMap {
+        ProjectMap {
+            tree: dir(
+                ".",
+                vec![
+                    dir(
+                        "src",
+                        vec![dir("auth", vec![file("mod.rs")]), file("main.rs")],
+                    ),
+                    file("README.md"),
+                ],
+            ),
+            total_files: 3,
+            total_dirs: 3,
+        }
+    }
+
+    const COUNTER: TokenCounter = TokenCounter::Heuristic;
+
+    fn options(root: &str) -> MapOptions {
+        MapOptions {
+            root: root.to_string(),
+            depth: 3,
+            inline: false,
+        }
+    }
+
+    #[test]
+    fn build_reads_dot_prefixed_keys() {
+        let cache = cache(&["./src/main.rs", "./src/auth/mod.rs", "./README.md"]);
+
+        let map = ProjectMap::build(&cache, &options("")).unwrap();
+        assert_eq!(map.total_files, 3);
+        assert_eq!(map.tree.stats.unwrap().files, 3);
+        let children: Vec<&str> = map
+            .tree
+            .children
+            .iter()
+            .flatten()
+            .map(|child| child.path.as_str())
+            .collect();
+        assert_eq!(children, ["README.md", "src"]);
+
+        let map = ProjectMap::build(&cache, &options("src")).unwrap();
+        assert_eq!(map.total_files, 2);
+        assert_eq!(map.tree.stats.unwrap().files, 2);
+    }
+
+    #[test]
+    fn height_counts_built_levels() {
+        assert_eq!(height(&file("main.rs")), 0);
+        assert_eq!(height(&dir("empty", Vec::new())), 0);
+        assert_eq!(height(&project().tree), 3);
+        // Directories past the depth limit have no children
+        let cut_off = MapNode {
+            children: None,
+            ..dir("src", Vec::new())
+        };
+        assert_eq!(height(&dir(".", vec![cut_off])), 1);
+    }
+
+    #[test]
+    fn render_within_caps_depth_at_the_tree_height() {
+        let map = project();
+        let rendered = map.render_within(MapFormat::Tree, 10, None, COUNTER);
+        assert_eq!(rendered.depth, 3);
+        assert!(!rendered.truncated);
+        assert_eq!(rendered.content, map.render(MapFormat::Tree, 3));
+        assert_eq!(rendered.tokens, COUNTER.count(&rendered.content));
+    }
+
+    #[test]
+    fn render_within_picks_the_deepest_level_that_fits() {
+        let map = project();
+        for format in [MapFormat::Tree, MapFormat::Markdown] {
+            for depth in 0..=3 {
+                let budget = COUNTER.count(&map.render(format, depth));
+                let exact = map.render_within(format, 3, Some(budget), COUNTER);
+                assert_eq!(exact.depth, depth);
+                assert!(!exact.truncated);
+
+                if depth < 3 {
+                    let next = COUNTER.count(&map.render(format, depth + 1));
+                    let below = map.render_within(format, 3, Some(next - 1), COUNTER);
+                    assert_eq!(below.depth, depth);
+                    assert!(below.tokens < next);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn render_within_cuts_lines_when_the_root_does_not_fit() {
+        let map = ProjectMap {
+            tree: MapNode {
+                inline: (1..=4).map(note).collect(),
+                ..file("src/main.rs")
+            },
+            total_files: 1,
+            total_dirs: 0,
+        };
+        let full = map.render(MapFormat::Tree, 0);
+        let lines: Vec<&str> = full.lines().collect();
+        assert_eq!(lines.len(), 5);
+
+        let budget = COUNTER.count(&full) - 1;
+        let rendered = map.render_within(MapFormat::Tree, 3, Some(budget), COUNTER);
+        assert!(rendered.truncated);
+        assert_eq!(rendered.depth, 0);
+        assert!(rendered.tokens <= budget);
+
+        let kept: Vec<&str> = rendered.content.lines().collect();
+        let (last, kept) = kept.split_last().unwrap();
+        assert!(!kept.is_empty());
+        assert_eq!(kept, &lines[..kept.len()]);
+        assert_eq!(
+            *last,
+            format!("... (+{})", plural(lines.len() - kept.len(), "line"))
+        );
+    }
+
+    #[test]
+    fn render_within_zero_budget_keeps_only_the_note() {
+        let map = project();
+        let rendered = map.render_within(MapFormat::Tree, 3, Some(0), COUNTER);
+        assert!(rendered.truncated);
+        assert_eq!(rendered.depth, 0);
+        assert_eq!(rendered.content, "... (+1 line)");
+        assert_eq!(rendered.tokens, COUNTER.count("... (+1 line)"));
+    }
+}
@@ -0,0 +1,124 @@
+//! @acp:module "Origin Protection"
+//! @acp:summary "CORS allow-list and Host header validation"
+//! @acp:domain daemon
+//! @acp:layer transport
+//!
+//! Browsers will happily send requests to a daemon on localhost from any
+//! web page. Cross-origin reads are only allowed for configured origins,
+//! and requests whose Host header is not a known name are rejected to
+//! defeat DNS rebinding.
+
+use std::net::IpAddr;
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tower_http::cors::{AllowOrigin, Any, CorsLayer};
+use tracing::{debug, warn};
+
+use crate::api::ApiError;
+
+/// Host names that always refer to this machine
+const LOOPBACK_HOSTS: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// CORS layer for the configured origins
+///
+/// Returns `None` when no origins are configured, so browsers refuse all
+/// cross-origin requests. `*` allows any origin.
+pub fn cors_layer(origins: &[String]) -> Option<CorsLayer> {
+    if origins.is_empty() {
+        return None;
+    }
+
+    let allow_origin = if origins.iter().any(|o| o == "*") {
+        warn!("CORS allows any origin; any web page can read this daemon's API");
+        AllowOrigin::from(Any)
+    } else {
+        AllowOrigin::list(origins.iter().filter_map(|origin| {
+            HeaderValue::from_str(origin)
+                .map_err(|_| warn!("Ignoring invalid CORS origin '{}'", origin))
+                .ok()
+        }))
+    };
+
+    Some(
+        CorsLayer::new()
+            .allow_origin(allow_origin)
+            .allow_methods([Method::GET, Method::POST, Method::DELETE])
+            .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE]),
+    )
+}
+
+/// Set of Host header values the daemon answers to
+pub struct HostGuard {
+    allowed: Vec<String>,
+    allow_any: bool,
+}
+
+impl HostGuard {
+    /// Allow loopback names, the bind address and `extra` names (`*` allows any)
+    pub fn new(bind: IpAddr, extra: &[String]) -> Arc<Self> {
+        let mut allowed: Vec<String> = LOOPBACK_HOSTS.iter().map(|h| h.to_string()).collect();
+        if !bind.is_unspecified() && !bind.is_loopback() {
+            allowed.push(bind.to_string());
+        }
+        allowed.extend(extra.iter().map(|h| h.to_lowercase()));
+
+        Arc::new(Self {
+            allow_any: extra.iter().any(|h| h == "*"),
+            allowed,
+        })
+    }
+
+    fn allows(&self, host: &str) -> bool {
+        self.allow_any || self.allowed.iter().any(|h| h == host)
+    }
+}
+
+/// Middleware rejecting requests addressed to an unknown Host
+pub async fn validate_host(
+    State(guard): State<Arc<HostGuard>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|value| value.to_str().ok())
+        .or_else(|| req.uri().authority().map(|a| a.as_str()))
+        .map(host_name);
+
+    match host {
+        Some(host) if guard.allows(&host) => next.run(req).await,
+        Some(host) => {
+            debug!("Rejected request for Host '{}'", host);
+            ApiError::new(
+                StatusCode::FORBIDDEN,
+                "host_not_allowed",
+                format!("host '{}' is not allowed", host),
+            )
+            .with_suggestions(["Add the host name to the daemon's allowed_hosts setting"])
+            .into_response()
+        }
+        None => ApiError::bad_request("missing_host", "request has no Host header").into_response(),
+    }
+}
+
+/// Lowercased host without port or IPv6 brackets
+fn host_name(authority: &str) -> String {
+    let authority = authority.trim().to_lowercase();
+
+    if let Some(rest) = authority.strip_prefix('[') {
+        // [::1]:9222
+        return rest.split(']').next().unwrap_or_default().to_string();
+    }
+
+    match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host.to_string(),
+        _ => authority,
+    }
+}
@@ -0,0 +1,263 @@
+//! @acp:module "Path Resolution"
+//! @acp:summary "Deterministic mapping of client-supplied paths to indexed files"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Clients pass paths in many forms: `src/main.rs`, `./src/main.rs`,
+//! `/abs/project/src/main.rs`, `/src/main.rs` or just `main.rs`. Paths are
+//! normalised lexically, made relative to the project root, and then
+//! matched exactly or by suffix on path-segment boundaries. A suffix
+//! matching several files is reported as ambiguous instead of picking one.
+
+use std::collections::HashMap;
+use std::path::{Component, Path};
+
+use crate::suggest;
+
+/// Why a path could not be resolved to a single indexed file
+#[derive(Debug, thiserror::Error)]
+pub enum PathError {
+    #[error("path is empty")]
+    Empty,
+    #[error("path '{0}' is outside the project root")]
+    OutsideProject(String),
+    #[error("path '{path}' matches {} files", candidates.len())]
+    Ambiguous {
+        path: String,
+        candidates: Vec<String>,
+    },
+    #[error("file '{path}' not found")]
+    NotFound {
+        /// The normalised path
+        path: String,
+        suggestions: Vec<String>,
+    },
+}
+
+/// Resolve `input` to a key of `files`
+pub fn resolve<V>(
+    project_root: &Path,
+    files: &HashMap<String, V>,
+    input: &str,
+) -> Result<String, PathError> {
+    let path = normalize(project_root, input)?;
+    if path.is_empty() {
+        return Err(PathError::Empty);
+    }
+
+    if let Some((key, _)) = cache_entry(files, &path) {
+        return Ok(key.clone());
+    }
+
+    let suffix = format!("/{}", path);
+    let mut candidates: Vec<&String> = files.keys().filter(|f| f.ends_with(&suffix)).collect();
+    candidates.sort();
+
+    match candidates.as_slice() {
+        [only] => Ok(only.to_string()),
+        [] => Err(PathError::NotFound {
+            suggestions: suggest::closest(&path, files.keys().map(String::as_str)),
+            path,
+        }),
+        _ => Err(PathError::Ambiguous {
+            candidates: candidates.into_iter().cloned().collect(),
+            path,
+        }),
+    }
+}
+
+/// Cache key `key` without the `./` prefix that `acp index` writes
+pub fn strip_dot(key: &str) -> &str {
+    key.trim_start_matches("./")
+}
+
+/// Whether two project-relative paths name the same file, with or without `./`
+pub fn same_file(a: &str, b: &str) -> bool {
+    strip_dot(a) == strip_dot(b)
+}
+
+/// The entry of `map` keyed by project-relative `path`, with or without `./`
+pub fn cache_entry<'a, V>(map: &'a HashMap<String, V>, path: &str) -> Option<(&'a String, &'a V)> {
+    let path = strip_dot(path);
+    map.get_key_value(path)
+        .or_else(|| map.get_key_value(&format!("./{}", path)))
+}
+
+/// Project-relative, `/`-separated form of `input` without `.` or `..`
+pub fn normalize(project_root: &Path, input: &str) -> Result<String, PathError> {
+    let input = input.trim().replace('\\', "/");
+    let path = Path::new(&input);
+
+    let relative = if path.is_absolute() {
+        // Absolute paths not under the root are read as project-relative
+        strip_root(project_root, path).unwrap_or_else(|| Path::new(input.trim_start_matches('/')))
+    } else {
+        path
+    };
+
+    let mut segments: Vec<String> = Vec::new();
+    for component in relative.components() {
+        match component {
+            Component::Normal(segment) => segments.push(segment.to_string_lossy().into_owned()),
+            Component::CurDir => {}
+            Component::ParentDir => {
+                segments
+                    .pop()
+                    .ok_or_else(|| PathError::OutsideProject(input.clone()))?;
+            }
+            Component::RootDir | Component::Prefix(_) => {
+                return Err(PathError::OutsideProject(input.clone()))
+            }
+        }
+    }
+
+    Ok(segments.join("/"))
+}
+
+/// `path` relative to the project root, trying the root as given and canonicalised
+fn strip_root<'a>(project_root: &Path, path: &'a Path) -> Option<&'a Path> {
+    if let Ok(rest) = path.strip_prefix(project_root) {
+        return Some(rest);
+    }
+    let canonical = std::fs::canonicalize(project_root).ok()?;
+    path.strip_prefix(canonical).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROOT: &str = "/work/project";
+
+    fn normalized(input: &str) -> Result<String, PathError> {
+        normalize(Path::new(ROOT), input)
+    }
+
+    fn files(paths: &[&str]) -> HashMap<String, ()> {
+        paths.iter().map(|p| (p.to_string(), ())).collect()
+    }
+
+    #[test]
+    fn normalize_strips_dots_and_separators() {
+        assert_eq!(normalized("src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(normalized("./src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(normalized("  src//main.rs ").unwrap(), "src/main.rs");
+        assert_eq!(normalized("src\\auth\\mod.rs").unwrap(), "src/auth/mod.rs");
+        assert_eq!(normalized("src/auth/../main.rs").unwrap(), "src/main.rs");
+        assert_eq!(normalized("src/auth/").unwrap(), "src/auth");
+        assert_eq!(normalized(".").unwrap(), "");
+    }
+
+    #[test]
+    fn normalize_makes_absolute_paths_relative() {
+        assert_eq!(
+            normalized("/work/project/src/main.rs").unwrap(),
+            "src/main.rs"
+        );
+        assert_eq!(normalized("/work/project").unwrap(), "");
+    }
+
+    #[test]
+    fn normalize_reads_other_absolute_paths_as_project_relative() {
+        assert_eq!(normalized("/src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(
+            normalized("/work/project-other/file.rs").unwrap(),
+            "work/project-other/file.rs"
+        );
+    }
+
+    #[test]
+    fn normalize_rejects_paths_outside_the_root() {
+        for input in [
+            "..",
+            "../other/file.rs",
+            "src/../../file.rs",
+            "src/./../..",
+            "/../file.rs",
+            "/work/project/../other/file.rs",
+        ] {
+            assert!(
+                matches!(normalized(input), Err(PathError::OutsideProject(_))),
+                "{} should be outside the project",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_prefers_exact_then_unique_suffix() {
+        let files = files(&["src/main.rs", "src/auth/mod.rs", "tools/src/main.rs"]);
+        let root = Path::new(ROOT);
+
+        assert_eq!(resolve(root, &files, "src/main.rs").unwrap(), "src/main.rs");
+        assert_eq!(
+            resolve(root, &files, "auth/mod.rs").unwrap(),
+            "src/auth/mod.rs"
+        );
+        // Suffixes match whole segments only
+        assert!(matches!(
+            resolve(root, &files, "th/mod.rs"),
+            Err(PathError::NotFound { .. })
+        ));
+    }
+
+    #[test]
+    fn resolve_matches_dot_prefixed_keys() {
+        let files = files(&["./src/main.rs", "./tools/src/main.rs"]);
+        let root = Path::new(ROOT);
+
+        assert_eq!(
+            resolve(root, &files, "src/main.rs").unwrap(),
+            "./src/main.rs"
+        );
+        assert_eq!(
+            resolve(root, &files, "/work/project/tools/src/main.rs").unwrap(),
+            "./tools/src/main.rs"
+        );
+    }
+
+    #[test]
+    fn resolve_rejects_empty_paths() {
+        let files = files(&["src/main.rs"]);
+
+        for input in ["", " ", ".", "./", "/work/project"] {
+            assert!(
+                matches!(
+                    resolve(Path::new(ROOT), &files, input),
+                    Err(PathError::Empty)
+                ),
+                "'{}' should be rejected",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn resolve_reports_ambiguous_suffixes() {
+        let files = files(&["src/main.rs", "tools/src/main.rs", "tools/main.rs"]);
+
+        match resolve(Path::new(ROOT), &files, "main.rs") {
+            Err(PathError::Ambiguous { path, candidates }) => {
+                assert_eq!(path, "main.rs");
+                assert_eq!(
+                    candidates,
+                    ["src/main.rs", "tools/main.rs", "tools/src/main.rs"]
+                );
+            }
+            other => panic!("expected an ambiguous match, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn resolve_suggests_close_names() {
+        let files = files(&["src/main.rs", "src/auth/mod.rs"]);
+
+        match resolve(Path::new(ROOT), &files, "src/mian.rs") {
+            Err(PathError::NotFound { path, suggestions }) => {
+                assert_eq!(path, "src/mian.rs");
+                assert!(suggestions.contains(&"src/main.rs".to_string()));
+            }
+            other => panic!("expected not found, got {:?}", other),
+        }
+    }
+}
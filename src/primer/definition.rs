@@ -0,0 +1,364 @@
+//! @acp:module "Primer Definition"
+//! @acp:summary "Primer commands and templates, built in or loaded from the project"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Projects customise the primer with `.acp/primer.json`, or a `primer`
+//! section in `.acp.config.json`. Commands are merged into the built-in
+//! set by name, so a team can override a template, add house rules or
+//! custom tools, or `disable` built-ins, without forking the daemon.
+
+use std::collections::HashSet;
+use std::path::Path;
+
+use anyhow::Context;
+use serde::Deserialize;
+use tracing::info;
+
+use crate::primer::tokens::TokenCounter;
+
+/// Project primer definition file
+pub const PRIMER_FILE: &str = ".acp/primer.json";
+
+/// Project config file, whose `primer` section is used when there is no `PRIMER_FILE`
+const CONFIG_FILE: &str = ".acp.config.json";
+
+/// Commands available to the primer
+#[derive(Debug, Clone)]
+pub struct PrimerDefinition {
+    pub commands: Vec<Command>,
+    /// Token counter used when a request does not name one
+    pub tokenizer: TokenCounter,
+}
+
+/// Command documentation with tiered content
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Command {
+    pub name: String,
+    /// Always included, even over budget
+    #[serde(default)]
+    pub critical: bool,
+    /// Lower comes first
+    #[serde(default = "default_priority")]
+    pub priority: u32,
+    /// Capabilities the agent needs to use the command (empty: any)
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+    /// Templates per tier, printed verbatim under the command name
+    pub minimal: String,
+    #[serde(default)]
+    pub standard: Option<String>,
+    #[serde(default)]
+    pub full: Option<String>,
+}
+
+/// Contents of a primer definition file
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrimerFile {
+    /// Use only these commands instead of merging them into the built-ins
+    #[serde(default)]
+    replace: bool,
+    /// Built-in commands to leave out
+    #[serde(default)]
+    disable: Vec<String>,
+    #[serde(default)]
+    commands: Vec<Command>,
+    /// Default tokenizer: an encoding, model name or `heuristic`
+    #[serde(default)]
+    tokenizer: Option<String>,
+}
+
+fn default_priority() -> u32 {
+    100
+}
+
+impl PrimerDefinition {
+    /// Load the project's primer definition, falling back to the built-in commands
+    pub fn load(project_root: &Path) -> anyhow::Result<Self> {
+        let primer_path = project_root.join(PRIMER_FILE);
+        if primer_path.exists() {
+            let content = std::fs::read_to_string(&primer_path)?;
+            let file: PrimerFile = serde_json::from_str(&content)
+                .with_context(|| format!("invalid primer definition in {}", PRIMER_FILE))?;
+            return Self::from_file(file, PRIMER_FILE);
+        }
+
+        let config_path = project_root.join(CONFIG_FILE);
+        if config_path.exists() {
+            let content = std::fs::read_to_string(&config_path)?;
+            let mut config: serde_json::Value = serde_json::from_str(&content)?;
+            if let Some(section) = config.get_mut("primer").map(serde_json::Value::take) {
+                let file: PrimerFile = serde_json::from_value(section)
+                    .with_context(|| format!("invalid primer section in {}", CONFIG_FILE))?;
+                return Self::from_file(file, CONFIG_FILE);
+            }
+        }
+
+        Ok(Self::builtin())
+    }
+
+    /// Whether `path` (under the canonical `project_root`) can change the definition
+    pub fn is_source(project_root: &Path, path: &Path) -> bool {
+        path == project_root.join(PRIMER_FILE) || path == project_root.join(CONFIG_FILE)
+    }
+
+    /// The built-in command set
+    pub fn builtin() -> Self {
+        Self {
+            commands: builtin_commands(),
+            tokenizer: TokenCounter::default(),
+        }
+    }
+
+    fn from_file(file: PrimerFile, source: &str) -> anyhow::Result<Self> {
+        let mut problems = file.problems();
+
+        let mut commands = if file.replace {
+            Vec::new()
+        } else {
+            builtin_commands()
+        };
+        for name in file.disable.iter().filter(|_| !file.replace) {
+            let before = commands.len();
+            commands.retain(|cmd| &cmd.name != name);
+            if commands.len() == before {
+                problems.push(format!("disable: no built-in command named '{}'", name));
+            }
+        }
+        for command in file.commands {
+            match commands.iter_mut().find(|cmd| cmd.name == command.name) {
+                Some(existing) => *existing = command,
+                None => commands.push(command),
+            }
+        }
+        if commands.is_empty() {
+            problems.push("no commands left".to_string());
+        }
+        let tokenizer = match file.tokenizer.as_deref().map(str::parse) {
+            Some(Ok(tokenizer)) => tokenizer,
+            Some(Err(e)) => {
+                problems.push(format!("tokenizer: {}", e));
+                TokenCounter::default()
+            }
+            None => TokenCounter::default(),
+        };
+
+        if !problems.is_empty() {
+            anyhow::bail!(
+                "invalid primer definition in {}:\n  - {}",
+                source,
+                problems.join("\n  - ")
+            );
+        }
+
+        info!("Loaded {} primer commands from {}", commands.len(), source);
+        Ok(Self {
+            commands,
+            tokenizer,
+        })
+    }
+}
+
+impl PrimerFile {
+    fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        let mut seen = HashSet::new();
+
+        for (i, cmd) in self.commands.iter().enumerate() {
+            let at = format!("commands[{}]", i);
+            if cmd.name.trim().is_empty() {
+                problems.push(format!("{}: name is empty", at));
+            } else if !seen.insert(cmd.name.as_str()) {
+                problems.push(format!("{}: duplicate command '{}'", at, cmd.name));
+            }
+
+            let tiers = [
+                ("minimal", Some(&cmd.minimal)),
+                ("standard", cmd.standard.as_ref()),
+                ("full", cmd.full.as_ref()),
+            ];
+            for (tier, template) in tiers {
+                if template.is_some_and(|template| template.trim().is_empty()) {
+                    problems.push(format!("{}: {} template is empty", at, tier));
+                }
+            }
+
+            if cmd.capabilities.iter().any(|cap| cap.trim().is_empty()) {
+                problems.push(format!("{}: capabilities contain an empty name", at));
+            }
+        }
+        if self.replace && !self.disable.is_empty() {
+            problems.push("disable has no effect with replace".to_string());
+        }
+
+        problems
+    }
+}
+
+fn shell_command(
+    name: &str,
+    priority: u32,
+    minimal: &str,
+    standard: Option<&str>,
+    full: Option<&str>,
+) -> Command {
+    Command {
+        name: name.to_string(),
+        critical: false,
+        priority,
+        capabilities: vec!["shell".to_string()],
+        minimal: minimal.to_string(),
+        standard: standard.map(String::from),
+        full: full.map(String::from),
+    }
+}
+
+fn mcp_tool(
+    name: &str,
+    priority: u32,
+    minimal: &str,
+    standard: Option<&str>,
+    full: Option<&str>,
+) -> Command {
+    Command {
+        capabilities: vec!["mcp".to_string()],
+        ..shell_command(name, priority, minimal, standard, full)
+    }
+}
+
+/// Get the default command set for the primer
+fn builtin_commands() -> Vec<Command> {
+    shell_commands().into_iter().chain(mcp_tools()).collect()
+}
+
+/// `acp` CLI commands, for agents that can run a shell
+fn shell_commands() -> Vec<Command> {
+    vec![
+        Command {
+            critical: true,
+            ..shell_command(
+                "acp constraints <path>",
+                1,
+                "  Returns: lock level + directive",
+                Some("  Returns: lock level + directive\n  Levels: frozen (refuse), restricted (ask), normal (proceed)\n  Use: Check before ANY file modification"),
+                Some("  Returns: lock level + directive\n  Levels: frozen (refuse), restricted (ask), normal (proceed)\n  Use: Check before ANY file modification\n  Example:\n    $ acp constraints src/auth/session.ts\n    frozen - Core auth logic; security-critical"),
+            )
+        },
+        shell_command(
+            "acp query file <path>",
+            2,
+            "  Returns: purpose, constraints, symbols",
+            Some("  Returns: purpose, constraints, symbols, dependencies\n  Options: --json for machine-readable output\n  Use: Understand file context before working with it"),
+            Some("  Returns: purpose, constraints, symbols, dependencies\n  Options: --json for machine-readable output\n  Use: Understand file context before working with it\n  Example:\n    $ acp query file src/payments/processor.ts"),
+        ),
+        shell_command(
+            "acp query symbol <name>",
+            3,
+            "  Returns: signature, purpose, constraints, callers",
+            Some("  Returns: signature, purpose, constraints, callers/callees\n  Options: --json for machine-readable output\n  Use: Understand function/method before modifying"),
+            None,
+        ),
+        shell_command(
+            "acp query domain <name>",
+            4,
+            "  Returns: domain files, cross-cutting concerns",
+            Some("  Returns: domain files, cross-cutting concerns\n  Options: --json for machine-readable output\n  Use: Understand architectural boundaries"),
+            None,
+        ),
+        shell_command(
+            "acp map [path]",
+            5,
+            "  Returns: directory tree with purposes",
+            Some("  Returns: directory tree with purposes and constraints\n  Options: --depth N, --inline (show todos/hacks)\n  Use: Navigate unfamiliar codebase"),
+            None,
+        ),
+        shell_command(
+            "acp expand <text>",
+            6,
+            "  Expands $variable references to full paths",
+            Some("  Expands $variable references to full paths\n  Options: --mode inline|annotated\n  Use: Resolve variable shortcuts in instructions"),
+            None,
+        ),
+        shell_command(
+            "acp attempt start <id>",
+            7,
+            "  Creates checkpoint for safe experimentation",
+            Some("  Creates checkpoint for safe experimentation\n  Related: acp attempt fail <id>, acp attempt verify <id>\n  Use: Track and revert failed approaches"),
+            None,
+        ),
+        shell_command(
+            "acp primer --budget <N>",
+            8,
+            "  Get more context (this command)",
+            Some("  Get more context within token budget\n  Options: --capabilities shell,mcp\n  Use: Request more detailed primer"),
+            None,
+        ),
+    ]
+}
+
+/// Daemon tools, for agents that reach it over HTTP or through an MCP bridge
+fn mcp_tools() -> Vec<Command> {
+    vec![
+        Command {
+            critical: true,
+            ..mcp_tool(
+                "GET /constraints/{path}",
+                1,
+                "  Returns: lock_level + directive",
+                Some("  Returns: lock_level + directive\n  Levels: frozen (refuse), restricted (ask), normal (proceed)\n  Use: Check before ANY file modification"),
+                Some("  Returns: lock_level + directive\n  Levels: frozen (refuse), restricted (ask), normal (proceed)\n  Use: Check before ANY file modification\n  Example:\n    GET /constraints/src/auth/session.ts\n    {\"lock_level\": \"frozen\", ...}"),
+            )
+        },
+        mcp_tool(
+            "GET /files/{path}",
+            2,
+            "  Returns: purpose, exports, imports",
+            Some("  Returns: purpose, exports, imports, domains, layer\n  Use: Understand file context before working with it"),
+            None,
+        ),
+        mcp_tool(
+            "GET /symbols/{name}",
+            3,
+            "  Returns: signature, purpose, constraints",
+            Some("  Returns: signature, purpose, constraints\n  Related: GET /callers/{name}, GET /callees/{name}, GET /symbols/{name}/source\n  Use: Understand function/method before modifying"),
+            None,
+        ),
+        mcp_tool(
+            "GET /domains/{name}",
+            4,
+            "  Returns: domain files and symbols",
+            Some("  Returns: domain files and symbols\n  Related: GET /domains to list them\n  Use: Understand architectural boundaries"),
+            None,
+        ),
+        mcp_tool(
+            "GET /map",
+            5,
+            "  Returns: directory tree with purposes and locks",
+            Some("  Returns: directory tree with purposes, locks and TODO counts\n  Options: ?path=<dir>, ?depth=N, ?inline=true, ?format=tree&budget=N\n  Use: Navigate unfamiliar codebase"),
+            None,
+        ),
+        mcp_tool(
+            "GET /vars/{name}/expand",
+            6,
+            "  Expands a $variable to its value",
+            Some("  Expands a $variable to its value and source\n  Related: GET /vars to list them\n  Use: Resolve variable shortcuts in instructions"),
+            None,
+        ),
+        mcp_tool(
+            "GET /git/changes",
+            7,
+            "  Lists changed files and the locked code they touch",
+            Some("  Lists changed files and the locked code they touch\n  Options: ?base=<ref> (default HEAD)\n  Use: Review your edits before committing"),
+            None,
+        ),
+        mcp_tool(
+            "GET /primer?budget=<N>",
+            8,
+            "  Get more context (this primer)",
+            Some("  Get more context within token budget\n  Options: &capabilities=mcp, &format=markdown|xml|json\n  Use: Request more detailed primer"),
+            None,
+        ),
+    ]
+}
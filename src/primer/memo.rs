@@ -0,0 +1,99 @@
+//! @acp:module "Primer Memo"
+//! @acp:summary "Rendered primers memoised per request and cache generation"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Agents fetch the same primer at the start of every session, so rendered
+//! responses are kept until the cache or primer definition changes. Entries
+//! from older generations are dropped on the next lookup.
+
+use std::collections::hash_map::DefaultHasher;
+use std::collections::HashMap;
+use std::hash::{Hash, Hasher};
+use std::sync::{Arc, Mutex};
+
+use super::PrimerFormat;
+
+/// Distinct primer requests kept per generation
+const MAX_ENTRIES: usize = 64;
+
+/// Everything a rendered primer depends on besides the cache generation
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PrimerKey {
+    pub budget: u32,
+    /// Sorted and deduplicated, so the order they were given in does not matter
+    pub capabilities: Vec<String>,
+    pub tokenizer: &'static str,
+    pub format: PrimerFormat,
+    pub focus: Option<String>,
+}
+
+/// A serialised primer response
+#[derive(Debug)]
+pub struct Rendered {
+    /// Strong entity tag of `body`, quoted
+    pub etag: String,
+    pub body: String,
+}
+
+impl Rendered {
+    pub fn new(body: String) -> Self {
+        let mut hasher = DefaultHasher::new();
+        body.hash(&mut hasher);
+        Self {
+            etag: format!("\"{:016x}\"", hasher.finish()),
+            body,
+        }
+    }
+
+    /// Whether an `If-None-Match` header value matches this primer
+    pub fn matches(&self, if_none_match: &str) -> bool {
+        if_none_match.split(',').map(str::trim).any(|tag| {
+            // Weak comparison, as RFC 9110 asks for If-None-Match
+            tag == "*" || tag.trim_start_matches("W/") == self.etag
+        })
+    }
+}
+
+#[derive(Default)]
+pub struct PrimerMemo {
+    inner: Mutex<MemoInner>,
+}
+
+#[derive(Default)]
+struct MemoInner {
+    generation: u64,
+    entries: HashMap<PrimerKey, Arc<Rendered>>,
+}
+
+impl PrimerMemo {
+    pub fn get(&self, generation: u64, key: &PrimerKey) -> Option<Arc<Rendered>> {
+        let inner = self.lock();
+        if inner.generation != generation {
+            return None;
+        }
+        inner.entries.get(key).cloned()
+    }
+
+    pub fn insert(&self, generation: u64, key: PrimerKey, rendered: Rendered) -> Arc<Rendered> {
+        let rendered = Arc::new(rendered);
+        let mut inner = self.lock();
+        if inner.generation != generation {
+            // A request that started before a change must not evict newer entries
+            if generation < inner.generation {
+                return rendered;
+            }
+            inner.generation = generation;
+            inner.entries.clear();
+        }
+        if inner.entries.len() >= MAX_ENTRIES {
+            inner.entries.clear();
+        }
+        inner.entries.insert(key, rendered.clone());
+        rendered
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MemoInner> {
+        self.inner.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
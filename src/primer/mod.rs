@@ -0,0 +1,389 @@
+//! @acp:module "Primer"
+//! @acp:summary "AI bootstrap primer content"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! A primer is a list of sections, each a list of items costed in tokens
+//! as rendered. Items are packed into the budget by rank: the bootstrap
+//! block and critical commands always, then project sections, then the
+//! remaining commands. Sections are rendered in a fixed order regardless
+//! of packing order.
+
+pub mod definition;
+pub mod memo;
+pub mod sections;
+pub mod tokens;
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+
+use acp::cache::Cache;
+use serde::{Deserialize, Serialize};
+
+pub use definition::PrimerDefinition;
+use tokens::TokenCounter;
+
+/// Tier level for content selection
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tier {
+    Minimal,
+    Standard,
+    Full,
+}
+
+impl Tier {
+    pub fn from_budget(remaining: u32) -> Self {
+        if remaining < 80 {
+            Tier::Minimal
+        } else if remaining < 300 {
+            Tier::Standard
+        } else {
+            Tier::Full
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Tier::Minimal => "minimal",
+            Tier::Standard => "standard",
+            Tier::Full => "full",
+        }
+    }
+}
+
+/// How the primer is rendered
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PrimerFormat {
+    /// Plain text with bare section titles
+    #[default]
+    Text,
+    /// Sections as `##` headings
+    Markdown,
+    /// Sections wrapped in `<id>` tags
+    Xml,
+    /// Sections and items with their token costs, for callers to pack
+    Json,
+}
+
+impl PrimerFormat {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PrimerFormat::Text => "text",
+            PrimerFormat::Markdown => "markdown",
+            PrimerFormat::Xml => "xml",
+            PrimerFormat::Json => "json",
+        }
+    }
+
+    /// Item text as it appears in the rendered primer
+    fn item<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        match self {
+            PrimerFormat::Xml if text.contains(['&', '<', '>']) => Cow::Owned(
+                text.replace('&', "&amp;")
+                    .replace('<', "&lt;")
+                    .replace('>', "&gt;"),
+            ),
+            _ => Cow::Borrowed(text),
+        }
+    }
+}
+
+/// A titled group of primer items
+#[derive(Debug, Clone)]
+pub struct Section {
+    pub id: &'static str,
+    pub title: Option<&'static str>,
+    /// Packing rank; lower ranks are filled first
+    pub rank: u32,
+    /// Separate items with a blank line
+    pub spaced: bool,
+    pub items: Vec<Item>,
+}
+
+/// One unit of primer content
+#[derive(Debug, Clone, Serialize)]
+pub struct Item {
+    pub text: String,
+    /// Tokens for the item as rendered
+    pub tokens: u32,
+    /// Included even when over budget
+    pub required: bool,
+    /// Packed into the budget
+    pub included: bool,
+    /// Packing rank, overriding the section's
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rank: Option<u32>,
+}
+
+impl Item {
+    pub fn new(text: String) -> Self {
+        Self {
+            text,
+            tokens: 0,
+            required: false,
+            included: false,
+            rank: None,
+        }
+    }
+
+    pub fn required(mut self) -> Self {
+        self.required = true;
+        self
+    }
+
+    pub fn ranked(mut self, rank: u32) -> Self {
+        self.rank = Some(rank);
+        self
+    }
+}
+
+impl Section {
+    pub fn new(id: &'static str, title: Option<&'static str>, rank: u32) -> Self {
+        Self {
+            id,
+            title,
+            rank,
+            spaced: false,
+            items: Vec::new(),
+        }
+    }
+
+    pub fn included_items(&self) -> impl Iterator<Item = &Item> {
+        self.items.iter().filter(|item| item.included)
+    }
+
+    /// Tokens for the section's title, tags and separator, paid once when
+    /// the first item is included
+    pub fn overhead_tokens(&self, format: PrimerFormat, counter: TokenCounter) -> u32 {
+        counter.count(&format!("{}{}\n", self.open(format), self.close(format)))
+    }
+
+    fn open(&self, format: PrimerFormat) -> String {
+        match (format, self.title) {
+            (PrimerFormat::Xml, _) => format!("<{}>\n", self.id),
+            (PrimerFormat::Markdown, Some(title)) => format!("## {}\n", title),
+            (_, Some(title)) => format!("{}\n", title),
+            (_, None) => String::new(),
+        }
+    }
+
+    fn close(&self, format: PrimerFormat) -> String {
+        match format {
+            PrimerFormat::Xml => format!("</{}>\n", self.id),
+            _ => String::new(),
+        }
+    }
+
+    fn render(&self, format: PrimerFormat) -> String {
+        let mut out = self.open(format);
+        for (i, item) in self.included_items().enumerate() {
+            if self.spaced && i > 0 {
+                out.push('\n');
+            }
+            out.push_str(&format.item(&item.text));
+            out.push('\n');
+        }
+        out.push_str(&self.close(format));
+        out
+    }
+}
+
+/// What to build a primer for
+pub struct PrimerOptions {
+    pub budget: u32,
+    /// Only commands needing one of these (or none) are included; empty: shell
+    pub capabilities: Vec<String>,
+    pub tokenizer: TokenCounter,
+    pub format: PrimerFormat,
+    /// Path prefix or domain whose warnings come first
+    pub focus: Option<String>,
+}
+
+/// A primer packed into a token budget
+pub struct Primer {
+    pub budget: u32,
+    pub tier: Tier,
+    pub tokenizer: TokenCounter,
+    pub format: PrimerFormat,
+    /// Every candidate section in render order, items flagged if packed
+    pub sections: Vec<Section>,
+}
+
+impl Primer {
+    pub fn build(cache: &Cache, definition: &PrimerDefinition, options: &PrimerOptions) -> Self {
+        let counter = options.tokenizer;
+        let format = options.format;
+        let cost = |section| cost(section, format, counter);
+
+        // Agents that do not say otherwise get the shell commands
+        let shell = ["shell".to_string()];
+        let capabilities = if options.capabilities.is_empty() {
+            &shell[..]
+        } else {
+            &options.capabilities[..]
+        };
+
+        let bootstrap = cost(sections::bootstrap(capabilities));
+        let bootstrap_tokens = bootstrap.items.iter().map(|item| item.tokens).sum::<u32>();
+        let tier = Tier::from_budget(options.budget.saturating_sub(bootstrap_tokens));
+
+        let mut candidates: Vec<Section> = [
+            commands(definition, capabilities, tier),
+            sections::warnings(cache, tier, options.focus.as_deref()),
+            sections::domains(cache, tier),
+            sections::layout(cache, tier),
+            sections::hotspots(cache, tier),
+            sections::entry_points(cache, tier),
+        ]
+        .into_iter()
+        .map(cost)
+        .collect();
+        candidates.insert(0, bootstrap);
+        pack(&mut candidates, options.budget, format, counter);
+
+        Self {
+            budget: options.budget,
+            tier,
+            tokenizer: counter,
+            format,
+            sections: candidates,
+        }
+    }
+
+    /// Sections with at least one packed item
+    pub fn included(&self) -> impl Iterator<Item = &Section> {
+        included(&self.sections)
+    }
+
+    /// Rendered primer; JSON callers render items themselves, so it gets text
+    pub fn render(&self) -> String {
+        render(&self.sections, self.format)
+    }
+
+    /// Tokens charged while packing: included items plus section overhead
+    pub fn packed_tokens(&self) -> u32 {
+        packed_tokens(&self.sections, self.format, self.tokenizer)
+    }
+
+    pub fn section(&self, id: &str) -> Option<&Section> {
+        self.sections.iter().find(|section| section.id == id)
+    }
+}
+
+/// Sections with at least one packed item
+pub fn included(sections: &[Section]) -> impl Iterator<Item = &Section> {
+    sections
+        .iter()
+        .filter(|section| section.included_items().next().is_some())
+}
+
+/// Render the packed items of `sections`
+pub fn render(sections: &[Section], format: PrimerFormat) -> String {
+    included(sections)
+        .map(|section| section.render(format))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// Tokens charged while packing `sections`: included items plus section overhead
+pub fn packed_tokens(sections: &[Section], format: PrimerFormat, counter: TokenCounter) -> u32 {
+    included(sections)
+        .map(|section| {
+            section.overhead_tokens(format, counter)
+                + section
+                    .included_items()
+                    .map(|item| item.tokens)
+                    .sum::<u32>()
+        })
+        .sum()
+}
+
+/// Set each item's token cost as rendered in `format`
+pub fn cost(mut section: Section, format: PrimerFormat, counter: TokenCounter) -> Section {
+    // Each item is followed by a newline, and spaced items by a blank line
+    let spacing = if section.spaced { "\n\n" } else { "\n" };
+    for item in &mut section.items {
+        item.tokens = counter.count(&format!("{}{}", format.item(&item.text), spacing));
+    }
+    section
+}
+
+/// Flag the items that fit in `budget`, required items and lower ranks first
+pub fn pack(sections: &mut [Section], budget: u32, format: PrimerFormat, counter: TokenCounter) {
+    let mut order: Vec<(usize, usize)> = sections
+        .iter()
+        .enumerate()
+        .flat_map(|(s, section)| (0..section.items.len()).map(move |i| (s, i)))
+        .collect();
+    order.sort_by_key(|&(s, i)| {
+        let section = &sections[s];
+        let item = &section.items[i];
+        (!item.required, item.rank.unwrap_or(section.rank), s, i)
+    });
+
+    let mut used = 0;
+    let mut opened = vec![false; sections.len()];
+    for (s, i) in order {
+        let opening = if opened[s] {
+            0
+        } else {
+            sections[s].overhead_tokens(format, counter)
+        };
+        let item = &mut sections[s].items[i];
+        if item.required || used + opening + item.tokens <= budget {
+            used += opening + item.tokens;
+            item.included = true;
+            opened[s] = true;
+        }
+    }
+}
+
+/// Commands from the definition for the agent's capabilities, at the tier's detail
+fn commands(definition: &PrimerDefinition, capabilities: &[String], tier: Tier) -> Section {
+    let mut commands: Vec<_> = definition
+        .commands
+        .iter()
+        .filter(|cmd| {
+            cmd.capabilities.is_empty()
+                || cmd
+                    .capabilities
+                    .iter()
+                    .any(|cap| capabilities.contains(cap))
+        })
+        .collect();
+
+    // Sort by (critical desc, priority asc)
+    commands.sort_by(|a, b| match (a.critical, b.critical) {
+        (true, false) => Ordering::Less,
+        (false, true) => Ordering::Greater,
+        _ => a.priority.cmp(&b.priority),
+    });
+
+    let mut section = Section::new("commands", None, sections::COMMANDS_RANK);
+    section.spaced = true;
+    section.items = commands
+        .into_iter()
+        .map(|cmd| {
+            let template = match tier {
+                Tier::Full => cmd
+                    .full
+                    .as_ref()
+                    .or(cmd.standard.as_ref())
+                    .unwrap_or(&cmd.minimal),
+                Tier::Standard => cmd.standard.as_ref().unwrap_or(&cmd.minimal),
+                Tier::Minimal => &cmd.minimal,
+            };
+            let item = Item::new(format!("{}\n{}", cmd.name, template));
+            // Critical commands are always included
+            if cmd.critical {
+                item.required()
+            } else {
+                item
+            }
+        })
+        .collect();
+    section
+}
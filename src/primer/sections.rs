@@ -0,0 +1,385 @@
+//! @acp:module "Primer Sections"
+//! @acp:summary "Project-specific primer sections built from the cache"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Each section ranks its items and trims them to the tier: minimal keeps
+//! one-line summaries, standard adds counts, full adds purposes and paths.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use acp::cache::Cache;
+
+use super::{Item, Section, Tier};
+use crate::locks::{self, LOCK_LEVELS};
+
+/// Packing ranks, lowest first
+pub const BOOTSTRAP_RANK: u32 = 0;
+pub const WARNINGS_RANK: u32 = 1;
+pub const DOMAINS_RANK: u32 = 2;
+pub const LAYOUT_RANK: u32 = 3;
+pub const HOTSPOTS_RANK: u32 = 4;
+pub const ENTRY_POINTS_RANK: u32 = 5;
+pub const COMMANDS_RANK: u32 = 6;
+
+/// File stems whose summary describes their directory
+const INDEX_STEMS: &[&str] = &["mod", "index", "__init__", "lib", "main"];
+
+/// File stems that conventionally start a program or library
+const ENTRY_STEMS: &[&str] = &["main", "index", "app", "server", "cli", "lib"];
+
+/// Bootstrap block, always included
+///
+/// Points at the `acp` CLI, or at the daemon's tools for agents without a shell.
+pub fn bootstrap(capabilities: &[String]) -> Section {
+    let mut section = Section::new("bootstrap", None, BOOTSTRAP_RANK);
+    let has = |name: &str| capabilities.iter().any(|cap| cap == name);
+    let lines = if has("shell") || !has("mcp") {
+        [
+            "This project uses ACP. @acp:* comments are directives for you.",
+            "Before editing: acp constraints <path>",
+            "More: acp primer --budget N",
+        ]
+    } else {
+        [
+            "This project uses ACP. @acp:* comments are directives for you.",
+            "Before editing: GET /constraints/{path}",
+            "More: GET /primer?budget=N&capabilities=mcp",
+        ]
+    };
+    section.items = lines
+        .into_iter()
+        .map(|line| Item::new(line.to_string()).required())
+        .collect();
+    section
+}
+
+/// Frozen and restricted files and symbols, most important first
+///
+/// Ranked by relevance to `focus` (a path prefix or domain name), then
+/// severity, then number of callers.
+pub fn warnings(cache: &Cache, tier: Tier, focus: Option<&str>) -> Section {
+    let mut section = Section::new("warnings", Some("Project Warnings"), WARNINGS_RANK);
+    let limit = match tier {
+        Tier::Minimal => 3,
+        Tier::Standard => 5,
+        Tier::Full => 10,
+    };
+
+    let callers = |name: &str| {
+        cache
+            .graph
+            .as_ref()
+            .and_then(|graph| graph.reverse.get(name))
+            .map_or(0, |callers| callers.iter().collect::<BTreeSet<_>>().len())
+    };
+    let focus_domain = focus.and_then(|focus| cache.domains.get(focus));
+    let in_focus = |path: &str, symbol: Option<&str>| match focus {
+        None => false,
+        Some(focus) => {
+            let focus = focus.trim_end_matches('/');
+            path == focus
+                || path.starts_with(&format!("{}/", focus))
+                || focus_domain.is_some_and(|domain| {
+                    domain.files.iter().any(|f| f == path)
+                        || symbol.is_some_and(|s| domain.symbols.iter().any(|d| d == s))
+                })
+        }
+    };
+
+    let mut warnings: Vec<Warning> = Vec::new();
+    if let Some(index) = &cache.constraints {
+        for (severity, level) in LOCK_LEVELS.into_iter().enumerate() {
+            let mut files: Vec<&String> = index
+                .by_lock_level
+                .get(level)
+                .into_iter()
+                .flatten()
+                .collect();
+            files.sort();
+            files.dedup();
+            for path in files {
+                if locks::file_lock_level(index, path) != Some(level) {
+                    continue;
+                }
+                let callers = cache
+                    .symbols
+                    .values()
+                    .filter(|symbol| &symbol.file == path)
+                    .map(|symbol| callers(&symbol.name))
+                    .sum();
+                warnings.push(Warning {
+                    focused: in_focus(path, None),
+                    severity,
+                    callers,
+                    target: path.clone(),
+                    level,
+                    directive: index
+                        .by_file
+                        .get(path)
+                        .and_then(|constraints| constraints.directive.clone()),
+                });
+            }
+        }
+    }
+    for (name, symbol) in &cache.symbols {
+        let Some(constraints) = &symbol.constraints else {
+            continue;
+        };
+        let Some(severity) = LOCK_LEVELS
+            .iter()
+            .position(|level| *level == constraints.level)
+        else {
+            continue;
+        };
+        warnings.push(Warning {
+            focused: in_focus(&symbol.file, Some(name)),
+            severity,
+            callers: callers(name),
+            target: name.clone(),
+            level: LOCK_LEVELS[severity],
+            directive: Some(constraints.directive.clone()),
+        });
+    }
+
+    warnings.sort_by(|a, b| {
+        b.focused
+            .cmp(&a.focused)
+            .then(a.severity.cmp(&b.severity))
+            .then(b.callers.cmp(&a.callers))
+            .then_with(|| a.target.cmp(&b.target))
+    });
+
+    section.items = warnings
+        .into_iter()
+        .take(limit)
+        .map(|warning| {
+            let text = match warning.directive.filter(|d| !d.is_empty()) {
+                Some(directive) => format!(
+                    "  - {}: {} ({})",
+                    warning.target,
+                    warning.level,
+                    directive.chars().take(50).collect::<String>()
+                ),
+                None => format!("  - {}: {}", warning.target, warning.level),
+            };
+            Item::new(text)
+        })
+        .collect();
+    section
+}
+
+/// A locked file or symbol, with its ranking keys
+struct Warning {
+    focused: bool,
+    /// Index into `LOCK_LEVELS`
+    severity: usize,
+    callers: usize,
+    target: String,
+    level: &'static str,
+    directive: Option<String>,
+}
+
+/// Largest domains with their descriptions
+pub fn domains(cache: &Cache, tier: Tier) -> Section {
+    let mut section = Section::new("domains", Some("Domains"), DOMAINS_RANK);
+
+    let mut domains: Vec<_> = cache.domains.values().collect();
+    domains.sort_by(|a, b| {
+        (b.files.len() + b.symbols.len())
+            .cmp(&(a.files.len() + a.symbols.len()))
+            .then_with(|| a.name.cmp(&b.name))
+    });
+
+    section.items = match tier {
+        Tier::Minimal if domains.is_empty() => Vec::new(),
+        Tier::Minimal => {
+            let names: Vec<&str> = domains.iter().take(8).map(|d| d.name.as_str()).collect();
+            vec![Item::new(format!("  {}", names.join(", ")))]
+        }
+        Tier::Standard | Tier::Full => {
+            let limit = if tier == Tier::Full { 10 } else { 5 };
+            domains
+                .iter()
+                .take(limit)
+                .map(|domain| {
+                    let mut text = format!("  - {}", domain.name);
+                    if let Some(description) = &domain.description {
+                        text.push_str(&format!(": {}", description));
+                    }
+                    text.push_str(&format!(" ({} files)", domain.files.len()));
+                    if tier == Tier::Full && !domain.files.is_empty() {
+                        let mut files = domain.files.clone();
+                        files.sort();
+                        text.push_str(&format!(
+                            "\n    {}",
+                            files.iter().take(3).cloned().collect::<Vec<_>>().join(", ")
+                        ));
+                    }
+                    Item::new(text)
+                })
+                .collect()
+        }
+    };
+    section
+}
+
+/// Top-level directories with file counts and purposes
+///
+/// Uses the shallowest directory depth that splits the project into more
+/// than one group, so a tree with everything under `src/` shows `src/*`.
+pub fn layout(cache: &Cache, tier: Tier) -> Section {
+    let mut section = Section::new("layout", Some("Layout"), LAYOUT_RANK);
+    let limit = match tier {
+        Tier::Minimal => return section,
+        Tier::Standard => 6,
+        Tier::Full => 12,
+    };
+
+    let group_at = |depth: usize| {
+        let mut groups: BTreeMap<String, usize> = BTreeMap::new();
+        for path in cache.files.keys() {
+            let dirs: Vec<&str> = path.split('/').collect();
+            let dirs = &dirs[..dirs.len() - 1];
+            if !dirs.is_empty() {
+                *groups
+                    .entry(dirs[..depth.min(dirs.len())].join("/"))
+                    .or_default() += 1;
+            }
+        }
+        groups
+    };
+    let mut groups = group_at(1);
+    for depth in 2..=3 {
+        if groups.len() > 1 {
+            break;
+        }
+        groups = group_at(depth);
+    }
+
+    let mut groups: Vec<(String, usize)> = groups.into_iter().collect();
+    groups.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+
+    section.items = groups
+        .into_iter()
+        .take(limit)
+        .map(|(dir, files)| {
+            let mut text = format!("  - {}/ ({} files)", dir, files);
+            if tier == Tier::Full {
+                if let Some(purpose) = dir_purpose(cache, &dir) {
+                    text.push_str(&format!(": {}", purpose));
+                }
+            }
+            Item::new(text)
+        })
+        .collect();
+    section
+}
+
+/// Summary of a directory's index file (`mod.rs`, `index.ts`, ...)
+fn dir_purpose<'a>(cache: &'a Cache, dir: &str) -> Option<&'a str> {
+    INDEX_STEMS.iter().find_map(|stem| {
+        cache.files.iter().find_map(|(path, file)| {
+            let (parent, name) = path.rsplit_once('/')?;
+            let file_stem = name.split('.').next()?;
+            (parent == dir && file_stem == *stem)
+                .then(|| file.summary.as_deref().or(file.purpose.as_deref()))
+                .flatten()
+        })
+    })
+}
+
+/// Most-called symbols, from the reverse call graph
+pub fn hotspots(cache: &Cache, tier: Tier) -> Section {
+    let mut section = Section::new("hotspots", Some("Hotspots"), HOTSPOTS_RANK);
+    let limit = match tier {
+        Tier::Minimal => return section,
+        Tier::Standard => 5,
+        Tier::Full => 10,
+    };
+    let Some(graph) = &cache.graph else {
+        return section;
+    };
+
+    let mut called: Vec<(&String, usize)> = graph
+        .reverse
+        .iter()
+        .map(|(name, callers)| (name, callers.iter().collect::<BTreeSet<_>>().len()))
+        .filter(|(_, callers)| *callers > 0)
+        .collect();
+    called.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+
+    section.items = called
+        .into_iter()
+        .take(limit)
+        .map(|(name, callers)| {
+            let noun = if callers == 1 { "caller" } else { "callers" };
+            let mut text = format!("  - {} ({} {})", name, callers, noun);
+            if tier == Tier::Full {
+                if let Some(symbol) = cache.symbols.get(name) {
+                    text.push_str(&format!(" in {}", symbol.file));
+                    if let Some(purpose) = symbol.purpose.as_ref().or(symbol.summary.as_ref()) {
+                        text.push_str(&format!(": {}", purpose));
+                    }
+                }
+            }
+            Item::new(text)
+        })
+        .collect();
+    section
+}
+
+/// `main` functions and conventional entry files
+pub fn entry_points(cache: &Cache, tier: Tier) -> Section {
+    let mut section = Section::new("entry_points", Some("Entry Points"), ENTRY_POINTS_RANK);
+    let limit = match tier {
+        Tier::Minimal => return section,
+        Tier::Standard => 3,
+        Tier::Full => 6,
+    };
+
+    // (rank, label, purpose): main functions, then shallow entry files
+    let mut entries: Vec<(usize, String, Option<&str>)> = Vec::new();
+    let mut seen_files = BTreeSet::new();
+
+    let mut mains: Vec<_> = cache
+        .symbols
+        .values()
+        .filter(|symbol| symbol.name == "main")
+        .collect();
+    mains.sort_by(|a, b| a.file.cmp(&b.file));
+    for symbol in mains {
+        seen_files.insert(symbol.file.as_str());
+        let purpose = symbol.purpose.as_deref().or(symbol.summary.as_deref());
+        entries.push((0, format!("main ({})", symbol.file), purpose));
+    }
+
+    for (path, file) in &cache.files {
+        let stem = path
+            .rsplit('/')
+            .next()
+            .and_then(|name| name.split('.').next());
+        let depth = path.matches('/').count();
+        if depth <= 2
+            && stem.is_some_and(|s| ENTRY_STEMS.contains(&s))
+            && !seen_files.contains(path.as_str())
+        {
+            let purpose = file.summary.as_deref().or(file.purpose.as_deref());
+            entries.push((1 + depth, path.clone(), purpose));
+        }
+    }
+    entries.sort_by(|a, b| a.0.cmp(&b.0).then_with(|| a.1.cmp(&b.1)));
+
+    section.items = entries
+        .into_iter()
+        .take(limit)
+        .map(|(_, label, purpose)| {
+            let text = match purpose {
+                Some(purpose) if tier == Tier::Full => format!("  - {}: {}", label, purpose),
+                _ => format!("  - {}", label),
+            };
+            Item::new(text)
+        })
+        .collect();
+    section
+}
@@ -0,0 +1,86 @@
+//! @acp:module "Token Counting"
+//! @acp:summary "Counts tokens in rendered primer text"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Byte-pair encoders with vocabularies bundled in the binary (loaded once,
+//! on first use), plus a cheap characters-per-token heuristic for models
+//! whose tokenizer is not public.
+
+use std::str::FromStr;
+
+use tiktoken_rs::tokenizer::{self, Tokenizer};
+use tiktoken_rs::CoreBPE;
+
+/// How tokens are counted
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TokenCounter {
+    /// GPT-4 / GPT-3.5 family; a reasonable approximation for other models
+    #[default]
+    Cl100k,
+    /// GPT-4o / o-series family
+    O200k,
+    /// Codex family
+    P50k,
+    /// GPT-3 family
+    R50k,
+    /// About four characters per token
+    Heuristic,
+}
+
+/// Names accepted for each counter, besides model names
+pub const COUNTER_NAMES: &[&str] = &[
+    "cl100k_base",
+    "o200k_base",
+    "p50k_base",
+    "r50k_base",
+    "heuristic",
+];
+
+impl TokenCounter {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Cl100k => "cl100k_base",
+            Self::O200k => "o200k_base",
+            Self::P50k => "p50k_base",
+            Self::R50k => "r50k_base",
+            Self::Heuristic => "heuristic",
+        }
+    }
+
+    /// Number of tokens in `text`
+    pub fn count(&self, text: &str) -> u32 {
+        let bpe: &CoreBPE = match self {
+            Self::Cl100k => tiktoken_rs::cl100k_base_singleton(),
+            Self::O200k => tiktoken_rs::o200k_base_singleton(),
+            Self::P50k => tiktoken_rs::p50k_base_singleton(),
+            Self::R50k => tiktoken_rs::r50k_base_singleton(),
+            Self::Heuristic => return (text.chars().count() as u32).div_ceil(4),
+        };
+        bpe.encode_ordinary(text).len() as u32
+    }
+}
+
+impl FromStr for TokenCounter {
+    type Err = String;
+
+    /// Parse an encoding name (`o200k_base`, `o200k`), a model name (`gpt-4o`) or `heuristic`
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let name = s.trim().to_lowercase();
+        let counter = match name.trim_end_matches("_base") {
+            "cl100k" => Self::Cl100k,
+            "o200k" => Self::O200k,
+            "p50k" => Self::P50k,
+            "r50k" | "gpt2" => Self::R50k,
+            "heuristic" => Self::Heuristic,
+            model => match tokenizer::get_tokenizer(model) {
+                Some(Tokenizer::Cl100kBase) => Self::Cl100k,
+                Some(Tokenizer::O200kBase) => Self::O200k,
+                Some(Tokenizer::P50kBase | Tokenizer::P50kEdit) => Self::P50k,
+                Some(Tokenizer::R50kBase | Tokenizer::Gpt2) => Self::R50k,
+                None => return Err(format!("unknown tokenizer or model '{}'", s)),
+            },
+        };
+        Ok(counter)
+    }
+}
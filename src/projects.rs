@@ -0,0 +1,320 @@
+//! @acp:module "Project Registry"
+//! @acp:summary "Multiple project roots served from one daemon process"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Each registered project gets its own `AppState` and router. Projects
+//! are loaded lazily on first request and evicted again when idle; the
+//! primary project (the one the daemon was started in) stays pinned.
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use axum::Router;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::RwLock;
+use tracing::{info, warn};
+
+use crate::server;
+use crate::state::AppState;
+use crate::watcher::{self, WatchHandle};
+
+/// How often the registry looks for idle projects
+const EVICTION_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Errors returned by registry operations
+#[derive(Debug, thiserror::Error)]
+pub enum ProjectError {
+    #[error("unknown project '{0}'")]
+    NotFound(String),
+    #[error("project root {0} is not a directory")]
+    InvalidRoot(PathBuf),
+    #[error("project root {root} is already registered as '{id}'")]
+    AlreadyRegistered { id: String, root: PathBuf },
+    #[error("project '{0}' is pinned and cannot be removed")]
+    Pinned(String),
+    #[error("failed to load project '{id}': {source}")]
+    Load { id: String, source: anyhow::Error },
+}
+
+/// Summary of a registered project, as served by `/projects`
+#[derive(Debug, Clone, Serialize)]
+pub struct ProjectInfo {
+    pub id: String,
+    pub root: PathBuf,
+    pub loaded: bool,
+    pub pinned: bool,
+    pub last_used: Option<DateTime<Utc>>,
+}
+
+/// Registry of projects served by this daemon
+#[derive(Clone)]
+pub struct ProjectRegistry {
+    inner: Arc<RegistryInner>,
+}
+
+struct RegistryInner {
+    projects: RwLock<BTreeMap<String, ProjectSlot>>,
+    /// Unload projects that have not been used for this long
+    idle_timeout: Duration,
+    /// Start a file watcher for each loaded project
+    watch: bool,
+}
+
+struct ProjectSlot {
+    root: PathBuf,
+    pinned: bool,
+    loaded: Option<LoadedProject>,
+    last_used: Option<(Instant, DateTime<Utc>)>,
+}
+
+struct LoadedProject {
+    state: AppState,
+    router: Router,
+    _watcher: Option<WatchHandle>,
+}
+
+impl ProjectSlot {
+    fn info(&self, id: &str) -> ProjectInfo {
+        ProjectInfo {
+            id: id.to_string(),
+            root: self.root.clone(),
+            loaded: self.loaded.is_some(),
+            pinned: self.pinned,
+            last_used: self.last_used.map(|(_, at)| at),
+        }
+    }
+
+    fn touch(&mut self) {
+        self.last_used = Some((Instant::now(), Utc::now()));
+    }
+}
+
+impl ProjectRegistry {
+    /// Create a registry whose primary project is already loaded
+    pub fn new(primary: AppState, idle_timeout: Duration, watch: bool) -> Self {
+        let root = canonical(primary.project_root());
+        let id = slug(&root);
+        let router = server::api_router(primary.clone());
+
+        let mut projects = BTreeMap::new();
+        projects.insert(
+            id,
+            ProjectSlot {
+                root,
+                pinned: true,
+                loaded: Some(LoadedProject {
+                    state: primary,
+                    router,
+                    // The primary project's watcher is owned by run_foreground
+                    _watcher: None,
+                }),
+                last_used: None,
+            },
+        );
+
+        Self {
+            inner: Arc::new(RegistryInner {
+                projects: RwLock::new(projects),
+                idle_timeout,
+                watch,
+            }),
+        }
+    }
+
+    /// List all registered projects
+    pub async fn list(&self) -> Vec<ProjectInfo> {
+        self.inner
+            .projects
+            .read()
+            .await
+            .iter()
+            .map(|(id, slot)| slot.info(id))
+            .collect()
+    }
+
+    /// Get a single project's summary
+    pub async fn info(&self, id: &str) -> Result<ProjectInfo, ProjectError> {
+        self.inner
+            .projects
+            .read()
+            .await
+            .get(id)
+            .map(|slot| slot.info(id))
+            .ok_or_else(|| ProjectError::NotFound(id.to_string()))
+    }
+
+    /// Register a project root; it is loaded on first use
+    pub async fn add(&self, root: &Path, id: Option<&str>) -> Result<ProjectInfo, ProjectError> {
+        if !root.is_dir() {
+            return Err(ProjectError::InvalidRoot(root.to_path_buf()));
+        }
+        let root = canonical(root);
+
+        let mut projects = self.inner.projects.write().await;
+        if let Some((existing, _)) = projects.iter().find(|(_, slot)| slot.root == root) {
+            return Err(ProjectError::AlreadyRegistered {
+                id: existing.clone(),
+                root,
+            });
+        }
+
+        let base = match id {
+            Some(id) => slugify(id),
+            None => slug(&root),
+        };
+        let mut id = base.clone();
+        let mut n = 1;
+        while projects.contains_key(&id) {
+            n += 1;
+            id = format!("{}-{}", base, n);
+        }
+
+        let slot = ProjectSlot {
+            root,
+            pinned: false,
+            loaded: None,
+            last_used: None,
+        };
+        let info = slot.info(&id);
+        projects.insert(id, slot);
+
+        info!(
+            "Registered project '{}' at {}",
+            info.id,
+            info.root.display()
+        );
+        Ok(info)
+    }
+
+    /// Unregister a project, unloading it if loaded
+    pub async fn remove(&self, id: &str) -> Result<ProjectInfo, ProjectError> {
+        let mut projects = self.inner.projects.write().await;
+        match projects.get(id) {
+            None => Err(ProjectError::NotFound(id.to_string())),
+            Some(slot) if slot.pinned => Err(ProjectError::Pinned(id.to_string())),
+            Some(_) => {
+                let slot = projects.remove(id).expect("checked above");
+                info!("Removed project '{}'", id);
+                Ok(slot.info(id))
+            }
+        }
+    }
+
+    /// Get the router for a project, loading it if necessary
+    pub async fn router(&self, id: &str) -> Result<Router, ProjectError> {
+        let root = {
+            let mut projects = self.inner.projects.write().await;
+            let slot = projects
+                .get_mut(id)
+                .ok_or_else(|| ProjectError::NotFound(id.to_string()))?;
+            slot.touch();
+            if let Some(loaded) = &slot.loaded {
+                return Ok(loaded.router.clone());
+            }
+            slot.root.clone()
+        };
+
+        // Load outside the lock so other projects stay responsive
+        info!("Loading project '{}' from {}", id, root.display());
+        let state = AppState::load(&root)
+            .await
+            .map_err(|source| ProjectError::Load {
+                id: id.to_string(),
+                source,
+            })?;
+
+        let mut projects = self.inner.projects.write().await;
+        let slot = projects
+            .get_mut(id)
+            .ok_or_else(|| ProjectError::NotFound(id.to_string()))?;
+        if let Some(loaded) = &slot.loaded {
+            // Another request finished loading first
+            return Ok(loaded.router.clone());
+        }
+
+        let watcher = if self.inner.watch {
+            watcher::spawn(state.clone())
+                .map_err(|e| warn!("File watching disabled for '{}': {}", id, e))
+                .ok()
+        } else {
+            None
+        };
+        let router = server::api_router(state.clone());
+        slot.loaded = Some(LoadedProject {
+            state,
+            router: router.clone(),
+            _watcher: watcher,
+        });
+
+        Ok(router)
+    }
+
+    /// Periodically unload projects that have been idle too long
+    pub fn spawn_eviction(&self) -> tokio::task::JoinHandle<()> {
+        let registry = self.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(EVICTION_INTERVAL);
+            loop {
+                interval.tick().await;
+                registry.evict_idle().await;
+            }
+        })
+    }
+
+    async fn evict_idle(&self) {
+        let idle_timeout = self.inner.idle_timeout;
+        let mut projects = self.inner.projects.write().await;
+
+        for (id, slot) in projects.iter_mut() {
+            if slot.pinned {
+                continue;
+            }
+            let idle = slot
+                .last_used
+                .map(|(at, _)| at.elapsed() >= idle_timeout)
+                .unwrap_or(true);
+            let busy = slot
+                .loaded
+                .as_ref()
+                .map(|l| l.state.jobs().has_running())
+                .unwrap_or(false);
+
+            if slot.loaded.is_some() && idle && !busy {
+                slot.loaded = None;
+                info!("Unloaded idle project '{}'", id);
+            }
+        }
+    }
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    std::fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Derive a URL-safe project id from the root directory name
+fn slug(root: &Path) -> String {
+    let name = root
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    slugify(&name)
+}
+
+fn slugify(name: &str) -> String {
+    let slug: String = name
+        .to_lowercase()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect();
+    let slug = slug.trim_matches('-');
+
+    if slug.is_empty() {
+        "project".to_string()
+    } else {
+        slug.to_string()
+    }
+}
@@ -5,15 +5,83 @@
 //!
 //! Provides the HTTP server configuration and router for the daemon API.
 
-use axum::{routing::get, Router};
-use tower_http::cors::{Any, CorsLayer};
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{any, get, post},
+    Router,
+};
+use tower_http::limit::RequestBodyLimitLayer;
+use tower_http::timeout::TimeoutLayer;
 use tower_http::trace::TraceLayer;
 
-use crate::api;
+use crate::api::{self, ApiError};
+use crate::auth::{self, Authenticator};
+use crate::origin::{self, HostGuard};
+use crate::projects::ProjectRegistry;
+use crate::settings::DaemonSettings;
 use crate::state::AppState;
 
 /// Create the main application router
-pub fn create_router(state: AppState) -> Router {
+///
+/// Serves the primary project at the root and every registered project
+/// under `/projects/{id}/...`. Requests must carry a valid bearer token
+/// unless `authenticator` is `None`, and must be addressed to an allowed
+/// Host.
+pub fn create_router(
+    state: AppState,
+    registry: ProjectRegistry,
+    authenticator: Option<Arc<Authenticator>>,
+    settings: &DaemonSettings,
+) -> Router {
+    let projects = Router::new()
+        .route(
+            "/projects",
+            get(api::projects::list_projects).post(api::projects::add_project),
+        )
+        .route(
+            "/projects/{id}",
+            get(api::projects::get_project).delete(api::projects::remove_project),
+        )
+        .route(
+            "/projects/{id}/{*rest}",
+            any(api::projects::forward_to_project),
+        )
+        .with_state(registry);
+
+    let mut router = api_router(state).merge(projects);
+    if let Some(authenticator) = authenticator {
+        router = router.layer(middleware::from_fn_with_state(
+            authenticator,
+            auth::require_auth,
+        ));
+    }
+
+    router = router
+        // Add middleware
+        .layer(RequestBodyLimitLayer::new(settings.limits.max_body_bytes))
+        .layer(TimeoutLayer::with_status_code(
+            StatusCode::REQUEST_TIMEOUT,
+            settings.limits.request_timeout,
+        ))
+        .layer(TraceLayer::new_for_http());
+    if let Some(cors) = origin::cors_layer(&settings.cors_origins) {
+        router = router.layer(cors);
+    }
+
+    // Outermost, so rebinding requests never reach CORS or auth
+    router.layer(middleware::from_fn_with_state(
+        HostGuard::new(settings.bind, &settings.allowed_hosts),
+        origin::validate_host,
+    ))
+}
+
+/// Create the per-project API router
+pub fn api_router(state: AppState) -> Router {
     Router::new()
         // Health check
         .route("/health", get(api::health::health_check))
@@ -23,10 +91,16 @@ pub fn create_router(state: AppState) -> Router {
         .route("/vars", get(api::vars::get_vars))
         // Symbol queries
         .route("/symbols/{name}", get(api::symbols::get_symbol))
+        .route(
+            "/symbols/{name}/source",
+            get(api::source::get_symbol_source),
+        )
         .route("/symbols", get(api::symbols::list_symbols))
         // File queries
         .route("/files/{*path}", get(api::files::get_file))
         .route("/files", get(api::files::list_files))
+        // Source code
+        .route("/source/{*path}", get(api::source::get_source))
         // Graph queries
         .route("/callers/{symbol}", get(api::graph::get_callers))
         .route("/callees/{symbol}", get(api::graph::get_callees))
@@ -40,17 +114,37 @@ pub fn create_router(state: AppState) -> Router {
         )
         // Variable expansion
         .route("/vars/{name}/expand", get(api::vars::expand_variable))
+        // Indexing
+        .route("/index", post(api::index::start_index))
+        .route("/jobs/{id}", get(api::index::get_job))
         // Aggregate endpoints
         .route("/stats", get(api::stats::get_stats))
+        .route("/staleness", get(api::staleness::get_staleness))
+        .route("/git/changes", get(api::git::get_changes))
         .route("/map", get(api::map::get_map))
         .route("/primer", get(api::primer::get_primer))
-        // Add middleware
-        .layer(TraceLayer::new_for_http())
-        .layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any),
-        )
+        .route("/context", post(api::context::build_context))
+        .fallback(route_not_found)
+        .layer(middleware::from_fn_with_state(
+            state.clone(),
+            reject_while_reloading,
+        ))
         .with_state(state)
 }
+
+/// Answer unknown routes with the JSON error envelope instead of an empty 404
+async fn route_not_found(req: Request) -> ApiError {
+    ApiError::route_not_found(req.method().as_str(), req.uri().path())
+}
+
+/// Answer 503 instead of serving a half-reloaded project
+async fn reject_while_reloading(
+    State(state): State<AppState>,
+    req: Request,
+    next: Next,
+) -> Response {
+    if state.is_reloading() && req.uri().path() != "/health" {
+        return ApiError::reloading().into_response();
+    }
+    next.run(req).await
+}
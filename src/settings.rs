@@ -0,0 +1,380 @@
+//! @acp:module "Daemon Settings"
+//! @acp:summary "Daemon settings resolved from CLI, environment and config"
+//! @acp:domain daemon
+//! @acp:layer config
+//!
+//! Settings come from four layers, highest precedence first:
+//! CLI flags, `ACPD_*` environment variables, the `daemon` section of
+//! `.acp.config.json`, and built-in defaults.
+
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+use anyhow::Context;
+use serde::{Deserialize, Deserializer};
+
+/// Log output format
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for LogFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            other => Err(anyhow::anyhow!(
+                "invalid log format '{}' (expected text or json)",
+                other
+            )),
+        }
+    }
+}
+
+/// Request limits applied to every route
+#[derive(Debug, Clone)]
+pub struct Limits {
+    /// Maximum request body size in bytes
+    pub max_body_bytes: usize,
+    /// Maximum time to handle a request
+    pub request_timeout: Duration,
+}
+
+/// Authentication settings
+#[derive(Debug, Clone)]
+pub struct AuthSettings {
+    /// Require a bearer token on API requests
+    pub enabled: bool,
+    /// File listing additional named API keys
+    pub keys_file: PathBuf,
+}
+
+/// TLS termination settings
+#[derive(Debug, Clone)]
+pub struct TlsSettings {
+    /// PEM certificate chain
+    pub cert: PathBuf,
+    /// PEM private key
+    pub key: PathBuf,
+    /// Require client certificates signed by these PEM CA certificates
+    pub client_ca: Option<PathBuf>,
+}
+
+/// Fully resolved daemon settings
+#[derive(Debug, Clone)]
+pub struct DaemonSettings {
+    /// Address to bind the HTTP listener to
+    pub bind: IpAddr,
+    /// HTTP port (0: pick a free port)
+    pub port: u16,
+    /// Serve on this Unix socket instead of TCP
+    pub socket: Option<PathBuf>,
+    /// Allowed CORS origins (empty: no cross-origin access)
+    pub cors_origins: Vec<String>,
+    /// Host header values accepted besides loopback names
+    pub allowed_hosts: Vec<String>,
+    pub log_level: String,
+    pub log_format: LogFormat,
+    pub limits: Limits,
+    pub auth: AuthSettings,
+    /// Serve HTTPS instead of plain HTTP
+    pub tls: Option<TlsSettings>,
+    /// Watch sources and re-index incrementally
+    pub watch: bool,
+    /// Additional project roots to serve
+    pub projects: Vec<PathBuf>,
+    /// Unload additional projects after this long without use
+    pub idle_timeout: Duration,
+}
+
+impl DaemonSettings {
+    /// Resolve settings for `project_root`, with `cli` taking precedence
+    pub fn resolve(cli: SettingsLayer, project_root: &Path) -> anyhow::Result<Self> {
+        let layer = cli
+            .or(SettingsLayer::from_env()?)
+            .or(SettingsLayer::from_config(project_root)?);
+
+        Ok(Self {
+            bind: layer.bind.unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            port: layer.port.unwrap_or(9222),
+            socket: layer.socket,
+            cors_origins: layer.cors_origins.unwrap_or_default(),
+            allowed_hosts: layer.allowed_hosts.unwrap_or_default(),
+            log_level: layer.log_level.unwrap_or_else(|| "info".to_string()),
+            log_format: layer.log_format.unwrap_or(LogFormat::Text),
+            limits: Limits {
+                max_body_bytes: layer.limits.max_body_bytes.unwrap_or(1024 * 1024),
+                request_timeout: Duration::from_secs(
+                    layer.limits.request_timeout_secs.unwrap_or(30),
+                ),
+            },
+            auth: AuthSettings {
+                enabled: layer.auth.enabled.unwrap_or(true),
+                keys_file: layer
+                    .auth
+                    .keys_file
+                    .unwrap_or_else(|| project_root.join(".acp").join("daemon.keys.json")),
+            },
+            tls: match (layer.tls.cert, layer.tls.key, layer.tls.client_ca) {
+                (Some(cert), Some(key), client_ca) => Some(TlsSettings {
+                    cert,
+                    key,
+                    client_ca,
+                }),
+                (None, None, None) => None,
+                _ => anyhow::bail!("TLS requires both a certificate and a private key"),
+            },
+            watch: layer.watch.unwrap_or(true),
+            projects: layer.projects.unwrap_or_default(),
+            idle_timeout: Duration::from_secs(layer.idle_timeout_secs.unwrap_or(600)),
+        })
+    }
+
+    /// Socket address for the TCP listener
+    pub fn addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+}
+
+/// One source of settings; unset fields fall through to the next layer
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SettingsLayer {
+    pub bind: Option<IpAddr>,
+    #[serde(deserialize_with = "deserialize_port")]
+    pub port: Option<u16>,
+    pub socket: Option<PathBuf>,
+    pub cors_origins: Option<Vec<String>>,
+    pub allowed_hosts: Option<Vec<String>>,
+    pub log_level: Option<String>,
+    pub log_format: Option<LogFormat>,
+    pub limits: LimitsLayer,
+    pub auth: AuthLayer,
+    pub tls: TlsLayer,
+    pub watch: Option<bool>,
+    pub projects: Option<Vec<PathBuf>>,
+    pub idle_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct LimitsLayer {
+    pub max_body_bytes: Option<usize>,
+    pub request_timeout_secs: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct AuthLayer {
+    pub enabled: Option<bool>,
+    pub keys_file: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TlsLayer {
+    pub cert: Option<PathBuf>,
+    pub key: Option<PathBuf>,
+    pub client_ca: Option<PathBuf>,
+}
+
+impl SettingsLayer {
+    /// Read the `daemon` section of `.acp.config.json`, if present
+    pub fn from_config(project_root: &Path) -> anyhow::Result<Self> {
+        let config_path = project_root.join(".acp.config.json");
+        if !config_path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(&config_path)?;
+        let mut config: serde_json::Value = serde_json::from_str(&content)?;
+        let mut layer: Self = match config.get_mut("daemon").map(serde_json::Value::take) {
+            Some(section) => serde_json::from_value(section)
+                .with_context(|| format!("invalid daemon section in {}", config_path.display()))?,
+            None => return Ok(Self::default()),
+        };
+
+        // Paths in the config file are relative to the project root
+        layer.socket = layer.socket.map(|p| project_root.join(p));
+        layer.auth.keys_file = layer.auth.keys_file.map(|p| project_root.join(p));
+        layer.tls.cert = layer.tls.cert.map(|p| project_root.join(p));
+        layer.tls.key = layer.tls.key.map(|p| project_root.join(p));
+        layer.tls.client_ca = layer.tls.client_ca.map(|p| project_root.join(p));
+        layer.projects = layer
+            .projects
+            .map(|roots| roots.into_iter().map(|p| project_root.join(p)).collect());
+
+        Ok(layer)
+    }
+
+    /// Read `ACPD_*` environment variables
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(Self {
+            bind: env_parse("ACPD_BIND")?,
+            port: env_var("ACPD_PORT")
+                .map(|v| parse_port(&v).map_err(|e| anyhow::anyhow!("invalid ACPD_PORT: {}", e)))
+                .transpose()?,
+            socket: env_var("ACPD_SOCKET").map(PathBuf::from),
+            cors_origins: env_var("ACPD_CORS_ORIGINS").map(|s| split_list(&s)),
+            allowed_hosts: env_var("ACPD_ALLOWED_HOSTS").map(|s| split_list(&s)),
+            log_level: env_var("ACPD_LOG_LEVEL"),
+            log_format: env_parse("ACPD_LOG_FORMAT")?,
+            limits: LimitsLayer {
+                max_body_bytes: env_parse("ACPD_MAX_BODY_BYTES")?,
+                request_timeout_secs: env_parse("ACPD_REQUEST_TIMEOUT")?,
+            },
+            auth: AuthLayer {
+                enabled: env_parse("ACPD_AUTH")?,
+                keys_file: env_var("ACPD_KEYS_FILE").map(PathBuf::from),
+            },
+            tls: TlsLayer {
+                cert: env_var("ACPD_TLS_CERT").map(PathBuf::from),
+                key: env_var("ACPD_TLS_KEY").map(PathBuf::from),
+                client_ca: env_var("ACPD_TLS_CLIENT_CA").map(PathBuf::from),
+            },
+            watch: env_parse("ACPD_WATCH")?,
+            projects: env_var("ACPD_PROJECTS").map(|s| std::env::split_paths(&s).collect()),
+            idle_timeout_secs: env_parse("ACPD_IDLE_TIMEOUT")?,
+        })
+    }
+
+    /// Merge with a lower-precedence layer
+    pub fn or(self, lower: SettingsLayer) -> SettingsLayer {
+        SettingsLayer {
+            bind: self.bind.or(lower.bind),
+            port: self.port.or(lower.port),
+            socket: self.socket.or(lower.socket),
+            cors_origins: self.cors_origins.or(lower.cors_origins),
+            allowed_hosts: self.allowed_hosts.or(lower.allowed_hosts),
+            log_level: self.log_level.or(lower.log_level),
+            log_format: self.log_format.or(lower.log_format),
+            limits: LimitsLayer {
+                max_body_bytes: self.limits.max_body_bytes.or(lower.limits.max_body_bytes),
+                request_timeout_secs: self
+                    .limits
+                    .request_timeout_secs
+                    .or(lower.limits.request_timeout_secs),
+            },
+            auth: AuthLayer {
+                enabled: self.auth.enabled.or(lower.auth.enabled),
+                keys_file: self.auth.keys_file.or(lower.auth.keys_file),
+            },
+            tls: TlsLayer {
+                cert: self.tls.cert.or(lower.tls.cert),
+                key: self.tls.key.or(lower.tls.key),
+                client_ca: self.tls.client_ca.or(lower.tls.client_ca),
+            },
+            watch: self.watch.or(lower.watch),
+            projects: self.projects.or(lower.projects),
+            idle_timeout_secs: self.idle_timeout_secs.or(lower.idle_timeout_secs),
+        }
+    }
+
+    /// `acpd run` arguments that reproduce this layer (for spawned daemons)
+    pub fn to_args(&self) -> Vec<String> {
+        let mut args = Vec::new();
+        if let Some(bind) = self.bind {
+            args.extend(["--bind".to_string(), bind.to_string()]);
+        }
+        if let Some(port) = self.port {
+            args.extend(["--port".to_string(), port.to_string()]);
+        }
+        if let Some(socket) = &self.socket {
+            args.extend(["--socket".to_string(), socket.display().to_string()]);
+        }
+        if let Some(level) = &self.log_level {
+            args.extend(["--log-level".to_string(), level.clone()]);
+        }
+        if let Some(format) = self.log_format {
+            let format = match format {
+                LogFormat::Text => "text",
+                LogFormat::Json => "json",
+            };
+            args.extend(["--log-format".to_string(), format.to_string()]);
+        }
+        if self.auth.enabled == Some(false) {
+            args.push("--no-auth".to_string());
+        }
+        if let Some(cert) = &self.tls.cert {
+            args.extend(["--tls-cert".to_string(), cert.display().to_string()]);
+        }
+        if let Some(key) = &self.tls.key {
+            args.extend(["--tls-key".to_string(), key.display().to_string()]);
+        }
+        if let Some(ca) = &self.tls.client_ca {
+            args.extend(["--tls-client-ca".to_string(), ca.display().to_string()]);
+        }
+        if self.watch == Some(false) {
+            args.push("--no-watch".to_string());
+        }
+        for project in self.projects.iter().flatten() {
+            args.extend(["--project".to_string(), project.display().to_string()]);
+        }
+        if let Some(secs) = self.idle_timeout_secs {
+            args.extend(["--idle-timeout".to_string(), secs.to_string()]);
+        }
+        args
+    }
+}
+
+/// Parse a port number, accepting `auto` for "pick a free port" (0)
+pub fn parse_port(s: &str) -> Result<u16, String> {
+    match s.trim() {
+        "auto" => Ok(0),
+        other => other
+            .parse()
+            .map_err(|_| format!("'{}' is not a port number or 'auto'", other)),
+    }
+}
+
+fn deserialize_port<'de, D>(deserializer: D) -> Result<Option<u16>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Port {
+        Number(u16),
+        Text(String),
+    }
+
+    match Option::<Port>::deserialize(deserializer)? {
+        None => Ok(None),
+        Some(Port::Number(port)) => Ok(Some(port)),
+        Some(Port::Text(text)) => parse_port(&text)
+            .map(Some)
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+fn env_var(name: &str) -> Option<String> {
+    std::env::var(name).ok().filter(|v| !v.trim().is_empty())
+}
+
+fn env_parse<T>(name: &str) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::fmt::Display,
+{
+    env_var(name)
+        .map(|v| {
+            v.trim()
+                .parse()
+                .map_err(|e| anyhow::anyhow!("invalid {}='{}': {}", name, v, e))
+        })
+        .transpose()
+}
+
+fn split_list(s: &str) -> Vec<String> {
+    s.split(',')
+        .map(|item| item.trim().to_string())
+        .filter(|item| !item.is_empty())
+        .collect()
+}
@@ -0,0 +1,162 @@
+//! @acp:module "Staleness"
+//! @acp:summary "Detects cache entries that drifted from the working tree"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! The cache records each file's modification time when it was indexed
+//! (`source_files`), but no size or content hash, so a file is considered
+//! modified when its mtime on disk is newer. Files missing from
+//! `source_files` are compared against the cache's `generated_at`.
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+use acp::cache::Cache;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::indexer::{self, SourceMatcher};
+use crate::paths;
+
+/// State of an indexed file compared to disk
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileState {
+    Fresh,
+    Modified,
+    Deleted,
+}
+
+/// Result of `GET /staleness`
+#[derive(Debug, Serialize)]
+pub struct StalenessReport {
+    /// Whether anything differs from the cache
+    pub stale: bool,
+    pub checked_at: DateTime<Utc>,
+    pub cache_generated_at: DateTime<Utc>,
+    /// Indexed files checked against disk
+    pub indexed: usize,
+    /// Indexed files changed on disk since indexing
+    pub modified: Vec<String>,
+    /// Indexed files that no longer exist
+    pub deleted: Vec<String>,
+    /// Source files on disk that are not in the cache
+    pub unindexed: Vec<String>,
+}
+
+/// A cache entry annotated with whether its file changed since indexing
+#[derive(Debug, Serialize)]
+pub struct WithStale<T> {
+    #[serde(flatten)]
+    pub entry: T,
+    pub stale: bool,
+}
+
+/// When `path` was last indexed, as far as the cache knows
+pub fn indexed_at(cache: &Cache, path: &str) -> DateTime<Utc> {
+    paths::cache_entry(&cache.source_files, path)
+        .map(|(_, at)| *at)
+        .unwrap_or(cache.generated_at)
+}
+
+/// Compare an indexed file against disk
+pub fn file_state(project_root: &Path, cache: &Cache, path: &str) -> FileState {
+    state_on_disk(&project_root.join(path), indexed_at(cache, path))
+}
+
+fn state_on_disk(full_path: &Path, indexed_at: DateTime<Utc>) -> FileState {
+    match std::fs::metadata(full_path).and_then(|m| m.modified()) {
+        Ok(modified) if DateTime::<Utc>::from(modified) > indexed_at => FileState::Modified,
+        Ok(_) => FileState::Fresh,
+        Err(_) => FileState::Deleted,
+    }
+}
+
+/// Per-request memo of file staleness, shared by entries in the same file
+pub struct StaleCheck<'a> {
+    project_root: &'a Path,
+    cache: &'a Cache,
+    seen: HashMap<String, bool>,
+}
+
+impl<'a> StaleCheck<'a> {
+    pub fn new(project_root: &'a Path, cache: &'a Cache) -> Self {
+        Self {
+            project_root,
+            cache,
+            seen: HashMap::new(),
+        }
+    }
+
+    /// Whether the file changed or disappeared since indexing
+    pub fn is_stale(&mut self, path: &str) -> bool {
+        if let Some(stale) = self.seen.get(path) {
+            return *stale;
+        }
+        let stale = file_state(self.project_root, self.cache, path) != FileState::Fresh;
+        self.seen.insert(path.to_string(), stale);
+        stale
+    }
+
+    /// Wrap `entry`, which belongs to `path`, with its staleness
+    pub fn annotate<T>(&mut self, path: &str, entry: T) -> WithStale<T> {
+        WithStale {
+            stale: self.is_stale(path),
+            entry,
+        }
+    }
+}
+
+/// What the cache knows about indexed files, detached from the cache lock
+///
+/// Paths are cache keys without their `./` prefix, as `discover_sources` reports them.
+pub struct IndexSnapshot {
+    generated_at: DateTime<Utc>,
+    files: BTreeMap<String, DateTime<Utc>>,
+}
+
+impl IndexSnapshot {
+    pub fn of(cache: &Cache) -> Self {
+        Self {
+            generated_at: cache.generated_at,
+            files: cache
+                .files
+                .keys()
+                .map(|key| (paths::strip_dot(key).to_string(), indexed_at(cache, key)))
+                .collect(),
+        }
+    }
+}
+
+/// Compare a cache snapshot against the working tree
+///
+/// Walks the project, so call it from a blocking task.
+pub fn report(
+    project_root: PathBuf,
+    snapshot: IndexSnapshot,
+    matcher: &SourceMatcher,
+) -> StalenessReport {
+    let mut modified = Vec::new();
+    let mut deleted = Vec::new();
+    for (path, indexed_at) in &snapshot.files {
+        match state_on_disk(&project_root.join(path), *indexed_at) {
+            FileState::Fresh => {}
+            FileState::Modified => modified.push(path.clone()),
+            FileState::Deleted => deleted.push(path.clone()),
+        }
+    }
+
+    let unindexed: Vec<String> = indexer::discover_sources(&project_root, matcher)
+        .into_iter()
+        .filter(|path| !snapshot.files.contains_key(path))
+        .collect();
+
+    StalenessReport {
+        stale: !(modified.is_empty() && deleted.is_empty() && unindexed.is_empty()),
+        checked_at: Utc::now(),
+        cache_generated_at: snapshot.generated_at,
+        indexed: snapshot.files.len(),
+        modified,
+        deleted,
+        unindexed,
+    }
+}
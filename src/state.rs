@@ -6,14 +6,21 @@
 //! Manages the loaded ACP schemas (config, cache, vars) and provides
 //! thread-safe access for request handlers.
 
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::sync::Arc;
 
 use acp::cache::Cache;
 use acp::config::Config;
 use acp::vars::VarsFile;
-use tokio::sync::RwLock;
-use tracing::{info, warn};
+use tokio::sync::{Mutex, MutexGuard, RwLock};
+use tracing::{debug, info, warn};
+
+use crate::jobs::JobRegistry;
+use crate::primer::memo::PrimerMemo;
+use crate::primer::PrimerDefinition;
 
 /// Shared application state for the daemon
 #[derive(Clone)]
@@ -28,8 +35,20 @@ struct AppStateInner {
     config: RwLock<Config>,
     /// Loaded ACP cache
     cache: RwLock<Cache>,
+    /// Held while the cache file is written or reloaded
+    cache_file: Mutex<CacheFile>,
     /// Loaded ACP vars
     vars: RwLock<Option<VarsFile>>,
+    /// Primer commands (built-in or project-defined)
+    primer: RwLock<PrimerDefinition>,
+    /// Bumped whenever the cache or primer definition changes
+    generation: AtomicU64,
+    /// Rendered primers for the current generation
+    primer_memo: PrimerMemo,
+    /// Background jobs (indexing)
+    jobs: JobRegistry,
+    /// Set while schemas are being reloaded from disk
+    reloading: AtomicBool,
 }
 
 impl AppState {
@@ -78,31 +97,26 @@ impl AppState {
             None
         };
 
+        // Load primer definition (built-in unless the project defines one)
+        let primer = PrimerDefinition::load(project_root)?;
+
         Ok(Self {
             inner: Arc::new(AppStateInner {
                 project_root: project_root.to_path_buf(),
                 config: RwLock::new(config),
                 cache: RwLock::new(cache),
+                cache_file: Mutex::new(CacheFile::default()),
                 vars: RwLock::new(vars),
+                primer: RwLock::new(primer),
+                generation: AtomicU64::new(0),
+                primer_memo: PrimerMemo::default(),
+                jobs: JobRegistry::default(),
+                reloading: AtomicBool::new(false),
             }),
         })
     }
 
-    /// Create AppState for testing with in-memory cache
-    #[cfg(test)]
-    pub fn for_testing(cache: Cache, vars: Option<VarsFile>) -> Self {
-        Self {
-            inner: Arc::new(AppStateInner {
-                project_root: std::path::PathBuf::from("."),
-                config: RwLock::new(Config::default()),
-                cache: RwLock::new(cache),
-                vars: RwLock::new(vars),
-            }),
-        }
-    }
-
     /// Get project root
-    #[allow(dead_code)]
     pub fn project_root(&self) -> &Path {
         &self.inner.project_root
     }
@@ -117,29 +131,103 @@ impl AppState {
         self.inner.cache.read().await
     }
 
+    /// Get write access to cache (for incremental indexing)
+    pub async fn cache_mut(&self) -> tokio::sync::RwLockWriteGuard<'_, Cache> {
+        let guard = self.inner.cache.write().await;
+        self.bump_generation();
+        guard
+    }
+
+    /// Counter that changes whenever the cache or primer definition does
+    ///
+    /// Read it while holding the cache and primer read guards; writers bump
+    /// it under their write guard, so it always matches what was read.
+    pub fn generation(&self) -> u64 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    fn bump_generation(&self) {
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+    }
+
+    /// Rendered primers memoised for the current generation
+    pub fn primer_memo(&self) -> &PrimerMemo {
+        &self.inner.primer_memo
+    }
+
+    /// Path of the cache file on disk
+    pub fn cache_path(&self) -> PathBuf {
+        self.inner.project_root.join(".acp").join("acp.cache.json")
+    }
+
+    /// Path of the vars file on disk
+    pub fn vars_path(&self) -> PathBuf {
+        self.inner.project_root.join(".acp").join("acp.vars.json")
+    }
+
+    /// Lock the cache file for writing; concurrent writers wait their turn
+    pub async fn cache_file(&self) -> MutexGuard<'_, CacheFile> {
+        self.inner.cache_file.lock().await
+    }
+
+    /// Get the background job registry
+    pub fn jobs(&self) -> &JobRegistry {
+        &self.inner.jobs
+    }
+
+    /// Whether a reload from disk is in progress
+    pub fn is_reloading(&self) -> bool {
+        self.inner.reloading.load(Ordering::Acquire)
+    }
+
     /// Get read access to vars
     pub async fn vars(&self) -> tokio::sync::RwLockReadGuard<'_, Option<VarsFile>> {
         self.inner.vars.read().await
     }
 
-    /// Reload cache from disk (for hot-reload, Phase 4)
-    #[allow(dead_code)]
+    /// Get read access to the primer definition
+    pub async fn primer(&self) -> tokio::sync::RwLockReadGuard<'_, PrimerDefinition> {
+        self.inner.primer.read().await
+    }
+
+    /// Reload the primer definition, keeping the current one if the new one is invalid
+    pub async fn reload_primer(&self) -> anyhow::Result<()> {
+        let primer = PrimerDefinition::load(&self.inner.project_root)?;
+
+        let mut write_guard = self.inner.primer.write().await;
+        *write_guard = primer;
+        self.bump_generation();
+
+        info!("Primer definition reloaded");
+        Ok(())
+    }
+
+    /// Reload cache from disk after it was rewritten by `acp index`
+    ///
+    /// The daemon's own writes are recognised and skipped.
     pub async fn reload_cache(&self) -> anyhow::Result<()> {
-        let cache_path = self.inner.project_root.join(".acp").join("acp.cache.json");
-        let content = tokio::fs::read_to_string(&cache_path).await?;
+        let cache_file = self.cache_file().await;
+        let content = tokio::fs::read_to_string(self.cache_path()).await?;
+        if cache_file.is_last_written(&content) {
+            debug!("Cache file unchanged since the daemon wrote it");
+            return Ok(());
+        }
+
+        let _reloading = ReloadingGuard::set(&self.inner.reloading);
         let cache: Cache = serde_json::from_str(&content)?;
 
         let mut write_guard = self.inner.cache.write().await;
         *write_guard = cache;
+        self.bump_generation();
 
         info!("Cache reloaded from disk");
         Ok(())
     }
 
-    /// Reload vars from disk (for hot-reload, Phase 4)
-    #[allow(dead_code)]
+    /// Reload vars from disk after they were rewritten by `acp index`
     pub async fn reload_vars(&self) -> anyhow::Result<()> {
-        let vars_path = self.inner.project_root.join(".acp").join("acp.vars.json");
+        let _reloading = ReloadingGuard::set(&self.inner.reloading);
+        let vars_path = self.vars_path();
         if vars_path.exists() {
             let content = tokio::fs::read_to_string(&vars_path).await?;
             let vars: VarsFile = serde_json::from_str(&content)?;
@@ -152,3 +240,43 @@ impl AppState {
         Ok(())
     }
 }
+
+/// What the daemon last wrote to the cache file
+#[derive(Default)]
+pub struct CacheFile {
+    /// Hash of the content
+    last_written: Option<u64>,
+}
+
+impl CacheFile {
+    /// Record `content` as written, so its watch event does not trigger a reload
+    pub fn written(&mut self, content: &str) {
+        self.last_written = Some(content_hash(content));
+    }
+
+    fn is_last_written(&self, content: &str) -> bool {
+        self.last_written == Some(content_hash(content))
+    }
+}
+
+fn content_hash(content: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    content.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Marks the state as reloading until dropped
+struct ReloadingGuard<'a>(&'a AtomicBool);
+
+impl<'a> ReloadingGuard<'a> {
+    fn set(flag: &'a AtomicBool) -> Self {
+        flag.store(true, Ordering::Release);
+        Self(flag)
+    }
+}
+
+impl Drop for ReloadingGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, Ordering::Release);
+    }
+}
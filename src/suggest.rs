@@ -0,0 +1,177 @@
+//! @acp:module "Suggestions"
+//! @acp:summary "Near-miss name matching for \"did you mean\" hints"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Ranks candidate names against a query that matched nothing, combining
+//! edit distance with overlap of name tokens (`getUserById` and
+//! `user::get_by_id` share `get`, `user`, `by`, `id`).
+
+use std::collections::BTreeSet;
+
+/// How many suggestions to return
+const MAX_SUGGESTIONS: usize = 5;
+
+/// Candidates scoring below this are not worth suggesting
+const MIN_SCORE: f64 = 0.5;
+
+/// The closest `candidates` to `query`, best first
+///
+/// Ties are broken alphabetically so results are stable across runs.
+pub fn closest<'a, I>(query: &str, candidates: I) -> Vec<String>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let query_lower = query.to_lowercase();
+    let query_tail = last_segment(&query_lower);
+    let query_tokens = tokens(query);
+
+    let mut scored: Vec<(f64, &str)> = candidates
+        .into_iter()
+        .filter(|candidate| *candidate != query)
+        .filter_map(|candidate| {
+            let lower = candidate.to_lowercase();
+            let score = similarity(&query_lower, &lower)
+                .max(similarity(query_tail, last_segment(&lower)) * 0.9)
+                .max(overlap(&query_tokens, &tokens(candidate)));
+            (score >= MIN_SCORE).then_some((score, candidate))
+        })
+        .collect();
+
+    scored.sort_by(|a, b| b.0.total_cmp(&a.0).then_with(|| a.1.cmp(b.1)));
+    scored.dedup_by(|a, b| a.1 == b.1);
+    scored
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, name)| name.to_string())
+        .collect()
+}
+
+/// Edit-distance similarity in `0.0..=1.0`
+fn similarity(a: &str, b: &str) -> f64 {
+    let longest = a.chars().count().max(b.chars().count());
+    if longest == 0 {
+        return 0.0;
+    }
+    1.0 - levenshtein(a, b) as f64 / longest as f64
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + usize::from(ca != *cb);
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(row[j + 1] + 1);
+        }
+    }
+    row[b.len()]
+}
+
+/// Jaccard overlap of two token sets
+fn overlap(a: &BTreeSet<String>, b: &BTreeSet<String>) -> f64 {
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+    let shared = a.intersection(b).count();
+    shared as f64 / a.union(b).count() as f64
+}
+
+/// Lowercase words of a name, split on punctuation and camelCase
+pub fn tokens(name: &str) -> BTreeSet<String> {
+    let mut tokens = BTreeSet::new();
+    let mut current = String::new();
+    let mut prev_lower = false;
+
+    for c in name.chars() {
+        if !c.is_alphanumeric() {
+            prev_lower = false;
+            if !current.is_empty() {
+                tokens.insert(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if c.is_uppercase() && prev_lower && !current.is_empty() {
+            tokens.insert(std::mem::take(&mut current));
+        }
+        prev_lower = c.is_lowercase() || c.is_ascii_digit();
+        current.extend(c.to_lowercase());
+    }
+    if !current.is_empty() {
+        tokens.insert(current);
+    }
+    tokens
+}
+
+/// Last component of a qualified name or path
+fn last_segment(name: &str) -> &str {
+    name.rsplit(['/', ':', '#']).next().unwrap_or(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(words: &[&str]) -> BTreeSet<String> {
+        words.iter().map(|w| w.to_string()).collect()
+    }
+
+    #[test]
+    fn levenshtein_counts_edits() {
+        assert_eq!(levenshtein("", ""), 0);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("abc", ""), 3);
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("main", "mian"), 2);
+        // Characters, not bytes
+        assert_eq!(levenshtein("café", "cafe"), 1);
+    }
+
+    #[test]
+    fn tokens_split_on_punctuation_and_case() {
+        assert_eq!(tokens("getUserById"), set(&["get", "user", "by", "id"]));
+        assert_eq!(tokens("user::get_by_id"), set(&["get", "user", "by", "id"]));
+        assert_eq!(
+            tokens("src/auth/mod.rs"),
+            set(&["src", "auth", "mod", "rs"])
+        );
+        assert_eq!(tokens("parseV2Config"), set(&["parse", "v2", "config"]));
+        // Runs of capitals stay together
+        assert_eq!(tokens("HTTPServer"), set(&["httpserver"]));
+        assert!(tokens("").is_empty());
+        assert!(tokens("::/").is_empty());
+    }
+
+    #[test]
+    fn closest_ranks_best_first() {
+        let candidates = ["get_users", "delete_account", "getUser"];
+        assert_eq!(closest("get_user", candidates), ["getUser", "get_users"]);
+    }
+
+    #[test]
+    fn closest_breaks_ties_alphabetically() {
+        assert_eq!(closest("foo", ["fox", "fob"]), ["fob", "fox"]);
+        assert_eq!(closest("foo", ["fob", "fox"]), ["fob", "fox"]);
+    }
+
+    #[test]
+    fn closest_ignores_case_but_not_the_query_itself() {
+        assert_eq!(closest("GetUser", ["getuser", "GetUser"]), ["getuser"]);
+    }
+
+    #[test]
+    fn closest_handles_empty_input() {
+        assert!(closest("", ["main", "lib"]).is_empty());
+        assert!(closest("main", []).is_empty());
+    }
+
+    #[test]
+    fn closest_caps_the_number_of_suggestions() {
+        let candidates = ["mainA", "mainB", "mainC", "mainD", "mainE", "mainF"];
+        assert_eq!(closest("main", candidates).len(), MAX_SUGGESTIONS);
+    }
+}
@@ -0,0 +1,97 @@
+//! @acp:module "TLS"
+//! @acp:summary "TLS termination and self-signed certificate generation"
+//! @acp:domain daemon
+//! @acp:layer transport
+//!
+//! Loads the configured certificate and key into a rustls server config,
+//! optionally requiring client certificates signed by a given CA.
+//! `acpd tls-cert` writes a self-signed certificate into `.acp/tls/`.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum_server::tls_rustls::RustlsConfig;
+use rustls::pki_types::pem::PemObject;
+use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use rustls::server::WebPkiClientVerifier;
+use rustls::{RootCertStore, ServerConfig};
+use tracing::info;
+
+use crate::auth;
+use crate::settings::TlsSettings;
+
+/// Where `acpd tls-cert` writes the certificate
+pub const CERT_FILE: &str = ".acp/tls/cert.pem";
+/// Where `acpd tls-cert` writes the private key
+pub const KEY_FILE: &str = ".acp/tls/key.pem";
+
+/// Names every generated certificate is valid for
+const LOOPBACK_NAMES: &[&str] = &["localhost", "127.0.0.1", "::1"];
+
+/// Build the rustls config for the HTTPS listener
+pub fn server_config(settings: &TlsSettings) -> anyhow::Result<RustlsConfig> {
+    let provider = Arc::new(rustls::crypto::ring::default_provider());
+
+    let certs = CertificateDer::pem_file_iter(&settings.cert)
+        .and_then(|certs| certs.collect::<Result<Vec<_>, _>>())
+        .with_context(|| format!("failed to read certificate {}", settings.cert.display()))?;
+    let key = PrivateKeyDer::from_pem_file(&settings.key)
+        .with_context(|| format!("failed to read private key {}", settings.key.display()))?;
+
+    let builder = ServerConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()?;
+    let builder = match &settings.client_ca {
+        Some(ca) => {
+            let mut roots = RootCertStore::empty();
+            for cert in CertificateDer::pem_file_iter(ca)
+                .with_context(|| format!("failed to read client CA {}", ca.display()))?
+            {
+                roots.add(cert?)?;
+            }
+            info!("Requiring client certificates signed by {}", ca.display());
+            let verifier =
+                WebPkiClientVerifier::builder_with_provider(Arc::new(roots), provider).build()?;
+            builder.with_client_cert_verifier(verifier)
+        }
+        None => builder.with_no_client_auth(),
+    };
+
+    let mut config = builder
+        .with_single_cert(certs, key)
+        .context("certificate and private key do not match")?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(RustlsConfig::from_config(Arc::new(config)))
+}
+
+/// Write a self-signed certificate for loopback names plus `hosts`
+///
+/// Returns the certificate and key paths. Existing files are kept unless
+/// `force` is set.
+pub fn generate_self_signed(
+    project_root: &Path,
+    hosts: &[String],
+    force: bool,
+) -> anyhow::Result<(PathBuf, PathBuf)> {
+    let cert_path = project_root.join(CERT_FILE);
+    let key_path = project_root.join(KEY_FILE);
+    if !force && (cert_path.exists() || key_path.exists()) {
+        anyhow::bail!(
+            "{} already exists (use --force to replace it)",
+            cert_path.display()
+        );
+    }
+
+    let mut names: Vec<String> = LOOPBACK_NAMES.iter().map(|n| n.to_string()).collect();
+    names.extend(hosts.iter().cloned());
+
+    let generated = rcgen::generate_simple_self_signed(names)?;
+    if let Some(parent) = cert_path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(&cert_path, generated.cert.pem())?;
+    auth::write_private(&key_path, &generated.signing_key.serialize_pem())?;
+
+    Ok((cert_path, key_path))
+}
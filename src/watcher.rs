@@ -0,0 +1,108 @@
+//! @acp:module "File Watcher"
+//! @acp:summary "Watches the project tree and triggers incremental re-indexing"
+//! @acp:domain daemon
+//! @acp:layer service
+//!
+//! Wraps a `notify` watcher on the project root. File events are
+//! debounced and handed to the incremental indexer in batches; rewrites
+//! of the cache and vars files reload them.
+
+use std::collections::BTreeSet;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use notify::{EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tracing::{debug, error, info, warn};
+
+use crate::indexer;
+use crate::primer::PrimerDefinition;
+use crate::state::AppState;
+
+/// Quiet period before a batch of file events is re-indexed
+const DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Handle for a running watcher; dropping it stops watching
+pub struct WatchHandle {
+    _watcher: RecommendedWatcher,
+    task: JoinHandle<()>,
+}
+
+impl Drop for WatchHandle {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// Start watching the project root of `state` for source changes
+pub fn spawn(state: AppState) -> anyhow::Result<WatchHandle> {
+    let root = std::fs::canonicalize(state.project_root())?;
+    let (tx, rx) = mpsc::unbounded_channel::<PathBuf>();
+
+    let mut watcher =
+        notify::recommended_watcher(move |res: notify::Result<notify::Event>| match res {
+            Ok(event) => {
+                if matches!(event.kind, EventKind::Access(_)) {
+                    return;
+                }
+                for path in event.paths {
+                    let _ = tx.send(path);
+                }
+            }
+            Err(e) => warn!("File watch error: {}", e),
+        })?;
+    watcher.watch(&root, RecursiveMode::Recursive)?;
+
+    info!("Watching {} for changes", root.display());
+    let task = tokio::spawn(run(state, root, rx));
+
+    Ok(WatchHandle {
+        _watcher: watcher,
+        task,
+    })
+}
+
+async fn run(state: AppState, root: PathBuf, mut rx: mpsc::UnboundedReceiver<PathBuf>) {
+    while let Some(first) = rx.recv().await {
+        let mut pending = BTreeSet::new();
+        pending.insert(first);
+
+        // Collect everything that arrives within the debounce window
+        loop {
+            match tokio::time::timeout(DEBOUNCE, rx.recv()).await {
+                Ok(Some(path)) => {
+                    pending.insert(path);
+                }
+                Ok(None) => return,
+                Err(_) => break,
+            }
+        }
+
+        debug!("{} path(s) changed", pending.len());
+
+        // `acp index` rewrote the schemas; the daemon's own cache writes are skipped
+        let acp_dir = root.join(".acp");
+        if pending.contains(&acp_dir.join("acp.cache.json")) {
+            if let Err(e) = state.reload_cache().await {
+                warn!("Keeping loaded cache: {:#}", e);
+            }
+        }
+        if pending.contains(&acp_dir.join("acp.vars.json")) {
+            if let Err(e) = state.reload_vars().await {
+                warn!("Keeping loaded vars: {:#}", e);
+            }
+        }
+        if pending
+            .iter()
+            .any(|path| PrimerDefinition::is_source(&root, path))
+        {
+            if let Err(e) = state.reload_primer().await {
+                warn!("Keeping previous primer definition: {:#}", e);
+            }
+        }
+        if let Err(e) = indexer::apply_changes(&state, pending.into_iter().collect()).await {
+            error!("Incremental index failed: {}", e);
+        }
+    }
+}